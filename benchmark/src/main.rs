@@ -41,7 +41,7 @@ fn main() {
         None
     };
     let chosen_nodes = graph.generate_random_water_nodes(100);
-    let mut state = AlgorithmState::new(graph.raster_columns_count * graph.raster_rows_count);
+    let mut state = AlgorithmState::new(graph.node_count());
 
     if !execute_all {
         let g = if execution_type.uses_shortcut() {
@@ -127,6 +127,7 @@ fn main() {
         let mut differences = validate_results(&correct_results, &results, g, &chosen_nodes);
 
         print_statistics(&mut differences, &results, &chosen_nodes, &mut durations);
+        print_accuracy(g, &results, &chosen_nodes);
 
         let mut file = File::create("benchmark.txt").unwrap();
         write!(
@@ -190,6 +191,7 @@ fn main() {
             println!("\n{} statistics:", ExecutionType::get_strings()[i]);
             print_statistics(differences, results, &chosen_nodes, durations);
         }
+        print_accuracy(&graph, &correct_results, &chosen_nodes);
 
         for (i, algorithm) in ExecutionType::get_strings().iter().enumerate() {
             let mut file = File::create(format!("benchmark{}.txt", algorithm)).unwrap();
@@ -247,10 +249,9 @@ fn validate_results(
         let correct_result = &correct_results[i];
         let result = &results[i];
         assert_eq!(correct_result.distance.is_some(), result.distance.is_some());
-        if correct_result.distance.is_some() {
-            let d1 = correct_result.distance.unwrap();
+        if let Some(d1) = correct_result.distance {
             let d2 = result.distance.unwrap();
-            let diff = if d1 > d2 { d1 - d2 } else { d2 - d1 };
+            let diff = d1.abs_diff(d2);
             differences.push(diff as usize);
 
             if diff > 1000 {
@@ -269,10 +270,10 @@ fn validate_results(
 }
 
 fn print_statistics(
-    differences: &mut Vec<usize>,
+    differences: &mut [usize],
     results: &[PathResult],
     chosen_nodes: &[(usize, usize)],
-    durations: &mut Vec<Duration>,
+    durations: &mut [Duration],
 ) {
     {
         differences.sort_unstable();
@@ -281,10 +282,10 @@ fn print_statistics(
         let min = differences[0] as f64 / 1000.0;
         let max = differences[differences.len() - 1] as f64 / 1000.0;
 
-        let width = (max as f64).log10().ceil() as usize + 3;
+        let width = max.log10().ceil() as usize + 3;
         println!(
             "\nAverage diff/node:   {:>1$.3}km",
-            total as f64
+            total
                 / results
                     .iter()
                     .map(|x| x.path.as_ref().unwrap_or(&Vec::new()).len())
@@ -336,6 +337,45 @@ fn print_statistics(
     }
 }
 
+// Compares the route lengths to the great circle distances, routes on a raster with only four neighbors are
+// up to 41% longer even on the open sea
+fn print_accuracy(graph: &Graph, results: &[PathResult], chosen_nodes: &[(usize, usize)]) {
+    let mut detours: Vec<f64> = chosen_nodes
+        .iter()
+        .zip(results.iter())
+        .filter_map(|((start_node, end_node), result)| {
+            let great_circle_distance = Graph::calculate_distance(
+                graph.get_lon(*start_node),
+                graph.get_lat(*start_node),
+                graph.get_lon(*end_node),
+                graph.get_lat(*end_node),
+            );
+            match result.distance {
+                Some(distance) if great_circle_distance > 0 => {
+                    Some((distance as f64 / great_circle_distance as f64 - 1.0) * 100.0)
+                }
+                _ => None,
+            }
+        })
+        .collect();
+    if detours.is_empty() {
+        return;
+    }
+    detours.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+
+    println!(
+        "\nRoute length above great circle distance ({}-neighbor connectivity):",
+        graph.connectivity
+    );
+    println!(
+        "Average:  {:>7.2}%",
+        detours.iter().sum::<f64>() / detours.len() as f64
+    );
+    println!("Median:   {:>7.2}%", detours[detours.len() / 2]);
+    println!("Min:      {:>7.2}%", detours[0]);
+    println!("Max:      {:>7.2}%", detours[detours.len() - 1]);
+}
+
 fn get_milliseconds(duration: &Duration) -> f64 {
     duration.as_micros() as f64 / 1000.0
 }
@@ -185,7 +185,23 @@ fn create_graph(graph: &Graph, rects: &[(usize, usize, usize, usize)]) -> Graph
         edges: Vec::new(),
         raster_columns_count: graph.raster_columns_count,
         raster_rows_count: graph.raster_rows_count,
+        bounding_box: graph.bounding_box,
+        coordinates: Vec::new(),
+        connectivity: graph.connectivity,
         shortcut_rectangles: rects.to_vec(),
+        depths: graph.depths.clone(),
+        sea_areas: graph.sea_areas.clone(),
+        sea_area_names: graph.sea_area_names.clone(),
+        cost_model: None,
+        weather_model: None,
+        canals: Vec::new(),
+        canal_edges: Default::default(),
+        ice_masks: None,
+        fuel_model: None,
+        coastlines: Default::default(),
+        components: graph.components.clone(),
+        largest_component: graph.largest_component,
+        spatial_index: None,
     };
 
     for (node_id, node_edges) in edges.lock().unwrap().iter().enumerate() {
@@ -221,6 +237,15 @@ fn main() {
         return;
     }
     let graph = Graph::new_from_binfile(&args[2]);
+    if !graph.is_raster() {
+        println!("Shortcut rectangles can only be placed on raster graphs");
+        return;
+    }
+    // Knight moves jump over the border nodes of the rectangles
+    if graph.connectivity > 8 {
+        println!("Shortcut rectangles need a connectivity of at most 8");
+        return;
+    }
 
     if args[1] == "--create" {
         let mut rects = Vec::new();
@@ -0,0 +1,67 @@
+use std::{env, fs, str::FromStr};
+
+use route::export::{self, ExportFormat};
+use route::{AlgorithmState, ExecutionType, Graph, QueryOptions};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    if args.len() < 8 {
+        println!("Required: <Graph binary file> <execution type> <lon1> <lat1> <lon2> <lat2> <format> [output file] [route name]");
+        println!("Possible execution types:");
+        for s in ExecutionType::get_strings() {
+            println!("  - {}", s);
+        }
+        println!("Possible formats:");
+        for s in ExportFormat::get_strings() {
+            println!("  - {}", s);
+        }
+        return;
+    }
+
+    let execution_type = match FromStr::from_str(&args[2]) {
+        Ok(et) => et,
+        Err(()) => {
+            println!("Invalid execution type {}", &args[2]);
+            return;
+        }
+    };
+    let coordinates: Vec<f64> = args[3..7].iter().map(|c| c.parse().unwrap()).collect();
+    let format = match ExportFormat::from_str(&args[7]) {
+        Ok(format) => format,
+        Err(()) => {
+            println!("Invalid format {}", &args[7]);
+            return;
+        }
+    };
+    let output_file = args
+        .get(8)
+        .cloned()
+        .unwrap_or_else(|| format!("route.{}", format.get_extension()));
+    let name = args.get(9).map_or("Route", |n| n.as_str());
+
+    let graph = Graph::new_from_binfile(&args[1]);
+    let mut state = AlgorithmState::new(graph.node_count());
+    let route = match graph.find_path(
+        coordinates[0],
+        coordinates[1],
+        coordinates[2],
+        coordinates[3],
+        &execution_type,
+        &QueryOptions::default(),
+        &mut state,
+    ) {
+        Ok(route) => route,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+
+    fs::write(
+        &output_file,
+        export::export_route(&route.waypoints, name, &format),
+    )
+    .unwrap();
+    println!("Route written to {}", output_file);
+}
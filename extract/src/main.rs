@@ -1,5 +1,8 @@
 use rayon::prelude::*;
-use route::{Edge, Graph};
+use route::coastlines::CoastlineIndex;
+use route::raster::Raster;
+use route::zones::Zone;
+use route::{Edge, Graph, GLOBAL_BOUNDING_BOX, RASTER_NEIGHBOR_OFFSETS, UNKNOWN_DEPTH};
 use std::sync::atomic::AtomicUsize;
 use std::{
     collections::HashMap,
@@ -10,8 +13,11 @@ use std::{
     io::Error,
 };
 
-const GRAPH_ROWS_COUNT: usize = 800;
-const GRAPH_COLUMNS_COUNT: usize = 1250;
+mod quadtree;
+
+const DEFAULT_GRAPH_ROWS_COUNT: usize = 800;
+const DEFAULT_GRAPH_COLUMNS_COUNT: usize = 1250;
+const MAX_QUADTREE_LEVELS: u32 = 10;
 const FACTOR_INT: i32 = 10_000_000;
 const FACTOR: f64 = 10_000_000.0;
 const WATER: Coordinate = Coordinate {
@@ -63,7 +69,7 @@ struct Coasts {
 impl Coasts {
     fn new_from_pbffile(filename: &str) -> Self {
         println!("Creating coasts from pbf file: {}", filename);
-        let file = File::open(&filename).unwrap();
+        let file = File::open(filename).unwrap();
         let reader = BufReader::new(file);
 
         let mut pbf = osmpbfreader::OsmPbfReader::new(reader);
@@ -177,7 +183,7 @@ impl Coasts {
 
     fn new_from_binfile(filename: &str) -> Self {
         println!("Creating coasts from bin file: {}", filename);
-        let mut buf_reader = BufReader::new(File::open(&filename).unwrap());
+        let mut buf_reader = BufReader::new(File::open(filename).unwrap());
         let coasts: Self = bincode::deserialize_from(&mut buf_reader).unwrap();
         println!(
             "Created {} coasts from bin file",
@@ -188,7 +194,7 @@ impl Coasts {
 
     fn write_to_binfile(&self, filename: &str) {
         println!("Saving coasts to binary file: {}", filename);
-        let mut buf_writer = BufWriter::new(File::create(&filename).unwrap());
+        let mut buf_writer = BufWriter::new(File::create(filename).unwrap());
         bincode::serialize_into(&mut buf_writer, &self.actual_coasts).unwrap();
     }
 
@@ -217,7 +223,7 @@ impl Coasts {
         }
 
         let output_json = serde_json::to_string(&geo_json).unwrap();
-        fs::write(&filename, output_json).unwrap();
+        fs::write(filename, output_json).unwrap();
     }
 }
 
@@ -225,6 +231,7 @@ impl Coasts {
 struct Node {
     coordinate: Coordinate,
     is_water: bool,
+    depth: i16,
 }
 
 impl Node {
@@ -234,6 +241,10 @@ impl Node {
             self.is_water = false;
             return;
         }
+        // the northpole is the water reference point itself
+        if self.coordinate.lat == WATER.lat {
+            return;
+        }
         for coast in coasts.actual_coasts.iter() {
             if !(coast.leftmost <= self.coordinate.lon && self.coordinate.lon <= coast.rightmost) {
                 continue;
@@ -304,63 +315,122 @@ impl Node {
 #[derive(serde::Serialize, serde::Deserialize)]
 struct Nodes {
     nodes: Vec<Node>,
+    neighbors: Vec<Vec<usize>>, // Adjacency of nodes which aren't a raster, empty for raster nodes
 }
 
 impl Nodes {
-    //fn new_generate_equally_distributed() -> Nodes {
-    //    println!("Generating equally distributed nodes");
-    //    let mut nodes = Vec::new();
-
-    //    let node_count = 1000;
-    //    let a = 1.0 / node_count as f64;
-    //    let d = f64::sqrt(a);
-    //    let m_theta = f64::round(std::f64::consts::PI / d);
-    //    let d_theta = std::f64::consts::PI / m_theta;
-    //    let d_phi = a / d_theta;
-
-    //    for m in 0..(m_theta as isize) {
-    //        let theta = std::f64::consts::PI * (m as f64 + 0.5) / m_theta;
-    //        let m_phi = f64::round(2.0 * std::f64::consts::PI * theta.sin() / d_phi);
-    //        for n in 0..(m_phi as isize) {
-    //            let phi = 2.0 * std::f64::consts::PI * n as f64 / m_phi;
-
-    //            let lat = theta.to_degrees() - 90.0;
-    //            let lon = phi.to_degrees() - 180.0;
-    //            nodes.push(Node {
-    //                coordinate: Coordinate {
-    //                    lon: (lon * FACTOR) as i32,
-    //                    lat: (lat * FACTOR) as i32,
-    //                },
-    //                is_water: true,
-    //            });
-    //        }
-    //    }
-    //    Nodes { nodes }
-    //}
-
-    fn new_generate_not_equally_distributed() -> Nodes {
-        println!("Generating not equally distributed nodes");
+    // Equal-area sampling of the sphere in latitude rings, each ring has as many nodes as fit with the
+    // same spacing as between the rings
+    fn new_generate_equally_distributed(node_count: usize) -> Nodes {
+        println!("Generating {} equally distributed nodes", node_count);
         let mut nodes = Vec::new();
+        let mut rings = Vec::new();
+
+        let a = 4.0 * std::f64::consts::PI / node_count as f64;
+        let d = f64::sqrt(a);
+        let m_theta = f64::round(std::f64::consts::PI / d);
+        let d_theta = std::f64::consts::PI / m_theta;
+        let d_phi = a / d_theta;
+
+        for m in 0..(m_theta as isize) {
+            let theta = std::f64::consts::PI * (m as f64 + 0.5) / m_theta;
+            let m_phi = f64::round(2.0 * std::f64::consts::PI * theta.sin() / d_phi).max(1.0);
+            rings.push((nodes.len(), m_phi as usize));
+            for n in 0..(m_phi as isize) {
+                let phi = 2.0 * std::f64::consts::PI * n as f64 / m_phi;
+
+                let lat = theta.to_degrees() - 90.0;
+                let lon = phi.to_degrees() - 180.0;
+                nodes.push(Node {
+                    coordinate: Coordinate {
+                        lon: (lon * FACTOR) as i32,
+                        lat: (lat * FACTOR) as i32,
+                    },
+                    is_water: true,
+                    depth: UNKNOWN_DEPTH,
+                });
+            }
+        }
+        println!("Generated {} nodes in {} rings", nodes.len(), rings.len());
+        let neighbors = Self::get_ring_neighbors(&rings, nodes.len());
+        Nodes { nodes, neighbors }
+    }
 
-        for lat in (-90 * FACTOR_INT..90 * FACTOR_INT)
-            .step_by((180.0 * FACTOR / GRAPH_ROWS_COUNT as f64) as usize)
-            .rev()
-        {
-            for lon in (-180 * FACTOR_INT..180 * FACTOR_INT)
-                .step_by((360.0 * FACTOR / GRAPH_COLUMNS_COUNT as f64) as usize)
-            {
+    // Neighbors of equally distributed nodes (given offset and node count of each latitude ring) are the
+    // previous and next node of the same ring and the nodes with the closest longitudes in the rings above
+    // and below. Neighborhoods are made symmetric.
+    fn get_ring_neighbors(rings: &[(usize, usize)], node_count: usize) -> Vec<Vec<usize>> {
+        let mut neighbors = vec![Vec::new(); node_count];
+        for (r, (offset, count)) in rings.iter().enumerate() {
+            for j in 0..*count {
+                let node = offset + j;
+                if *count > 1 {
+                    neighbors[node].push(offset + (j + 1) % count);
+                    neighbors[node].push(offset + (j + count - 1) % count);
+                }
+                for other in [r.wrapping_sub(1), r + 1] {
+                    let (other_offset, other_count) = match rings.get(other) {
+                        Some(ring) => *ring,
+                        None => continue,
+                    };
+                    let position = j as f64 * other_count as f64 / *count as f64;
+                    neighbors[node].push(other_offset + position.floor() as usize % other_count);
+                    neighbors[node].push(other_offset + position.ceil() as usize % other_count);
+                }
+            }
+        }
+
+        for node in 0..neighbors.len() {
+            for i in 0..neighbors[node].len() {
+                let neighbor = neighbors[node][i];
+                neighbors[neighbor].push(node);
+            }
+        }
+        for (node, node_neighbors) in neighbors.iter_mut().enumerate() {
+            node_neighbors.sort_unstable();
+            node_neighbors.dedup();
+            node_neighbors.retain(|n| *n != node);
+        }
+        neighbors
+    }
+
+    // Rows start at the top of the bounding box, the coordinates match Graph::get_lon and Graph::get_lat
+    fn new_generate_not_equally_distributed(
+        columns_count: usize,
+        rows_count: usize,
+        bounding_box: (f64, f64, f64, f64),
+    ) -> Nodes {
+        println!(
+            "Generating not equally distributed nodes ({}x{} in {:?})",
+            columns_count, rows_count, bounding_box
+        );
+        let mut nodes = Vec::with_capacity(columns_count * rows_count);
+
+        let (min_lon, min_lat, max_lon, max_lat) = bounding_box;
+        // Products of the steps and indices exceed i32 on global rasters, only the coordinates fit
+        let lon_step = ((max_lon - min_lon) * FACTOR / columns_count as f64) as i64;
+        let lat_step = ((max_lat - min_lat) * FACTOR / rows_count as f64) as i64;
+        for row in 0..rows_count as i64 {
+            let lat = ((max_lat * FACTOR).round() as i64 - row * lat_step) as i32;
+            for column in 0..columns_count as i64 {
+                let lon = ((min_lon * FACTOR).round() as i64 + column * lon_step) as i32;
                 nodes.push(Node {
                     coordinate: Coordinate { lon, lat },
                     is_water: true,
+                    depth: UNKNOWN_DEPTH,
                 });
             }
         }
-        Nodes { nodes }
+        Nodes {
+            nodes,
+            neighbors: Vec::new(),
+        }
     }
 
+    #[allow(dead_code)]
     fn new_from_binfile(filename: &str) -> Self {
         println!("Creating nodes from bin file: {}", filename);
-        let mut buf_reader = BufReader::new(File::open(&filename).unwrap());
+        let mut buf_reader = BufReader::new(File::open(filename).unwrap());
         let nodes: Self = bincode::deserialize_from(&mut buf_reader).unwrap();
         println!("Created {} nodes", nodes.nodes.len());
         nodes
@@ -368,7 +438,7 @@ impl Nodes {
 
     fn write_to_binfile(&self, filename: &str) {
         println!("Saving nodes to binary file: {}", filename);
-        let mut buf_writer = BufWriter::new(File::create(&filename).unwrap());
+        let mut buf_writer = BufWriter::new(File::create(filename).unwrap());
         bincode::serialize_into(&mut buf_writer, &self).unwrap();
     }
 
@@ -399,43 +469,93 @@ impl Nodes {
         }
 
         let output_json = serde_json::to_string(&geo_json).unwrap();
-        fs::write(&filename, output_json).unwrap();
+        fs::write(filename, output_json).unwrap();
     }
 
-    fn set_water_flags(&mut self, coasts: Coasts) {
+    fn set_water_flags(&mut self, coasts: &Coasts) {
         println!("Calculating the water flag for {} nodes:", self.nodes.len());
         let counter = AtomicUsize::new(0);
         self.nodes.par_iter_mut().for_each(|node| {
             let current_count = counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-            if current_count % 10000 == 0 {
+            if current_count.is_multiple_of(10000) {
                 println!("Finished {} nodes", current_count);
             }
 
-            node.set_water_flag(&coasts);
+            node.set_water_flag(coasts);
+        });
+    }
+
+    // Takes the depth of the bathymetry cell containing the node, elevations above sea level count as zero depth
+    fn set_depths(&mut self, bathymetry: &Raster) {
+        println!("Sampling the depth for {} nodes", self.nodes.len());
+        self.nodes.par_iter_mut().for_each(|node| {
+            node.depth =
+                match bathymetry.sample(node.coordinate.get_lon(), node.coordinate.get_lat()) {
+                    Some(elevation) => (-elevation).clamp(0.0, (UNKNOWN_DEPTH - 1) as f64) as i16,
+                    None => UNKNOWN_DEPTH,
+                };
         });
     }
 }
 
 trait GraphExt {
-    fn new_from_nodes(nodes: Nodes, raster_columns_count: usize, raster_rows_count: usize)
-        -> Graph;
-    fn get_neighbors(&self, i: usize) -> Vec<usize>;
+    fn new_from_nodes(
+        nodes: Nodes,
+        raster_columns_count: usize,
+        raster_rows_count: usize,
+        bounding_box: (f64, f64, f64, f64),
+        connectivity: u8,
+        coastline_index: &CoastlineIndex,
+        great_circle_edges: bool,
+    ) -> Graph;
+    fn get_neighbors(&self, i: usize, nodes: &[Node]) -> Vec<usize>;
 }
 
 impl GraphExt for Graph {
-    fn get_neighbors(&self, i: usize) -> Vec<usize> {
-        let mut neighbors = Vec::new();
-        let row = i / self.raster_columns_count;
+    // Diagonal and knight moves are only added if the nodes on both sides of the edge are water, so edges
+    // never cut a land corner
+    fn get_neighbors(&self, i: usize, nodes: &[Node]) -> Vec<usize> {
+        let row = (i / self.raster_columns_count) as isize;
+        let column = (i % self.raster_columns_count) as isize;
+        let columns_count = self.raster_columns_count as isize;
+        // Only global rasters wrap around at the antimeridian
+        let is_global = self.is_global();
+        let get_node = |row_offset: isize, column_offset: isize| {
+            let neighbor_row = row + row_offset;
+            let mut neighbor_column = column + column_offset;
+            if neighbor_row < 0 || neighbor_row >= self.raster_rows_count as isize {
+                return None;
+            }
+            if is_global {
+                neighbor_column = neighbor_column.rem_euclid(columns_count);
+            } else if neighbor_column < 0 || neighbor_column >= columns_count {
+                return None;
+            }
+            Some((neighbor_row * columns_count + neighbor_column) as usize)
+        };
+        let is_water = |row_offset: isize, column_offset: isize| {
+            get_node(row_offset, column_offset).is_some_and(|n| nodes[n].is_water)
+        };
 
-        if row > 0 {
-            neighbors.push(i - self.raster_columns_count);
-        }
-        if row < self.raster_rows_count - 1 {
-            neighbors.push(i + self.raster_columns_count);
+        let mut neighbors = Vec::new();
+        for (row_offset, column_offset) in &RASTER_NEIGHBOR_OFFSETS[..self.connectivity as usize] {
+            let (row_offset, column_offset) = (*row_offset, *column_offset);
+            let is_passable = if row_offset == 0 || column_offset == 0 {
+                true
+            } else if row_offset.abs() == column_offset.abs() {
+                is_water(row_offset, 0) && is_water(0, column_offset)
+            } else if row_offset.abs() == 2 {
+                is_water(row_offset / 2, 0) && is_water(row_offset / 2, column_offset)
+            } else {
+                is_water(0, column_offset / 2) && is_water(row_offset, column_offset / 2)
+            };
+            if !is_passable {
+                continue;
+            }
+            if let Some(neighbor) = get_node(row_offset, column_offset) {
+                neighbors.push(neighbor);
+            }
         }
-
-        neighbors.push(row * self.raster_columns_count + ((i + 1) % self.raster_columns_count));
-        neighbors.push(row * self.raster_columns_count + ((i - 1) % self.raster_columns_count));
         neighbors
     }
 
@@ -443,6 +563,10 @@ impl GraphExt for Graph {
         nodes: Nodes,
         raster_columns_count: usize,
         raster_rows_count: usize,
+        bounding_box: (f64, f64, f64, f64),
+        connectivity: u8,
+        coastline_index: &CoastlineIndex,
+        great_circle_edges: bool,
     ) -> Graph {
         println!("Generating graph from nodes");
         let mut graph = Graph {
@@ -450,20 +574,74 @@ impl GraphExt for Graph {
             edges: Vec::new(),
             raster_columns_count,
             raster_rows_count,
+            bounding_box,
+            coordinates: Vec::new(),
+            connectivity,
             shortcut_rectangles: Vec::new(),
+            depths: Vec::new(),
+            sea_areas: Vec::new(),
+            sea_area_names: Vec::new(),
+            cost_model: None,
+            weather_model: None,
+            canals: Vec::new(),
+            canal_edges: Default::default(),
+            ice_masks: None,
+            fuel_model: None,
+            coastlines: Default::default(),
+            components: Vec::new(),
+            largest_component: 0,
+            spatial_index: None,
         };
 
-        for (i, node) in nodes.nodes.iter().enumerate() {
-            graph.offsets.push((graph.edges.len() as u32, None));
-            if !node.is_water {
-                continue;
-            }
+        // Equally distributed and quadtree nodes aren't a raster, their coordinates are stored in the graph
+        if !nodes.neighbors.is_empty() {
+            graph.coordinates = nodes
+                .nodes
+                .iter()
+                .map(|n| [n.coordinate.get_lon(), n.coordinate.get_lat()])
+                .collect();
+        }
 
-            let neighbors = graph.get_neighbors(i);
-            for neighbor in neighbors {
-                if !nodes.nodes[neighbor].is_water {
-                    continue;
+        // Edges between water nodes can still cross narrow land like peninsulas or islands
+        let crossing_count = AtomicUsize::new(0);
+        let node_neighbors: Vec<Vec<usize>> = (0..nodes.nodes.len())
+            .into_par_iter()
+            .map(|i| {
+                if !nodes.nodes[i].is_water {
+                    return Vec::new();
                 }
+                let neighbors = if nodes.neighbors.is_empty() {
+                    graph.get_neighbors(i, &nodes.nodes)
+                } else {
+                    nodes.neighbors[i].clone()
+                };
+                neighbors
+                    .into_iter()
+                    .filter(|neighbor| nodes.nodes[*neighbor].is_water)
+                    .filter(|neighbor| {
+                        let p = [graph.get_lon(i), graph.get_lat(i)];
+                        let q = [graph.get_lon(*neighbor), graph.get_lat(*neighbor)];
+                        let crosses = if great_circle_edges {
+                            coastline_index.crosses_great_circle(p, q)
+                        } else {
+                            coastline_index.crosses_segment(p, q)
+                        };
+                        if crosses {
+                            crossing_count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                        }
+                        !crosses
+                    })
+                    .collect()
+            })
+            .collect();
+        println!(
+            "Dropped {} edges crossing the coastlines",
+            crossing_count.into_inner()
+        );
+
+        for (i, neighbors) in node_neighbors.into_iter().enumerate() {
+            graph.offsets.push((graph.edges.len() as u32, None));
+            for neighbor in neighbors {
                 let distance = Self::calculate_distance(
                     graph.get_lon(i),
                     graph.get_lat(i),
@@ -480,6 +658,12 @@ impl GraphExt for Graph {
         // Add another offset to the end to avoid checks on last edge iteration
         graph.offsets.push((graph.edges.len() as u32, None));
 
+        if nodes.nodes.iter().any(|n| n.depth != UNKNOWN_DEPTH) {
+            graph.depths = nodes.nodes.iter().map(|n| n.depth).collect();
+        }
+        graph.label_components();
+        graph.build_spatial_index();
+
         graph
     }
 }
@@ -515,57 +699,242 @@ fn east_or_west(clon: f64, dlon: f64) -> i32 {
     }
 }
 
+// Parses min_lon,min_lat,max_lon,max_lat, a longitude span of 360 degrees makes the raster wrap around
+fn parse_bounding_box(text: &str) -> Option<(f64, f64, f64, f64)> {
+    let values: Vec<f64> = text
+        .split(',')
+        .map(|v| v.trim().parse())
+        .collect::<Result<_, _>>()
+        .ok()?;
+    if values.len() != 4 {
+        return None;
+    }
+    let (min_lon, min_lat, max_lon, max_lat) = (values[0], values[1], values[2], values[3]);
+    if min_lon < -180.0
+        || max_lon > 180.0
+        || min_lat < -90.0
+        || max_lat > 90.0
+        || min_lon >= max_lon
+        || min_lat >= max_lat
+    {
+        return None;
+    }
+    Some((min_lon, min_lat, max_lon, max_lat))
+}
+
 fn main() -> Result<(), Error> {
     let args: Vec<String> = env::args().collect();
 
-    let file_name;
-    let skip_read_pbf;
-
-    match args.len() {
-        l if l < 2 => {
-            println!("Please pass a pbf file");
-            return Ok(());
-        }
-        l if l == 2 => {
-            file_name = &args[1];
-            skip_read_pbf = false;
-        }
-        l if l == 3 => {
-            if &args[1] == "-s" || &args[1] == "--skip-read-pbf" {
-                skip_read_pbf = true;
-                file_name = &args[2];
-            } else if &args[2] == "-s" || &args[2] == "--skip-read-pbf" {
-                file_name = &args[1];
-                skip_read_pbf = true;
-            } else {
-                println!("Invalid argument");
+    let mut file_name = None;
+    let mut skip_read_pbf = false;
+    let mut bathymetry_file = None;
+    let mut coastlines_file = None;
+    let mut sea_areas_file = None;
+    let mut rows_count = DEFAULT_GRAPH_ROWS_COUNT;
+    let mut columns_count = DEFAULT_GRAPH_COLUMNS_COUNT;
+    let mut bounding_box = GLOBAL_BOUNDING_BOX;
+    let mut equal_area_node_count = None;
+    let mut quadtree_levels = None;
+    let mut connectivity = None;
+    let mut great_circle_edges = false;
+
+    let mut args_iter = args.iter().skip(1);
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "-s" | "--skip-read-pbf" => skip_read_pbf = true,
+            "-g" | "--great-circle-edges" => great_circle_edges = true,
+            "-b" | "--bathymetry" => match args_iter.next() {
+                Some(f) => bathymetry_file = Some(f),
+                None => {
+                    println!("Please pass a bathymetry file");
+                    return Ok(());
+                }
+            },
+            "-c" | "--coastlines" => match args_iter.next() {
+                Some(f) => coastlines_file = Some(f),
+                None => {
+                    println!("Please pass a geojson file for the coastlines");
+                    return Ok(());
+                }
+            },
+            "-a" | "--sea-areas" => match args_iter.next() {
+                Some(f) => sea_areas_file = Some(f),
+                None => {
+                    println!("Please pass a geojson file for the sea areas");
+                    return Ok(());
+                }
+            },
+            "-r" | "--rows" => match args_iter.next().and_then(|r| r.parse().ok()) {
+                Some(r) if r > 1 => rows_count = r,
+                _ => {
+                    println!("Please pass a number of rows greater than 1");
+                    return Ok(());
+                }
+            },
+            "-l" | "--columns" => match args_iter.next().and_then(|c| c.parse().ok()) {
+                Some(c) if c > 1 => columns_count = c,
+                _ => {
+                    println!("Please pass a number of columns greater than 1");
+                    return Ok(());
+                }
+            },
+            "-x" | "--bbox" => match args_iter.next().and_then(|b| parse_bounding_box(b)) {
+                Some(b) => bounding_box = b,
+                None => {
+                    println!("Please pass a bounding box as min_lon,min_lat,max_lon,max_lat");
+                    return Ok(());
+                }
+            },
+            "-e" | "--equal-area" => match args_iter.next().and_then(|n| n.parse().ok()) {
+                Some(n) if n > 1 => equal_area_node_count = Some(n),
+                _ => {
+                    println!("Please pass a number of nodes greater than 1");
+                    return Ok(());
+                }
+            },
+            "-q" | "--quadtree" => match args_iter.next().and_then(|l| l.parse().ok()) {
+                Some(l) if (1..=MAX_QUADTREE_LEVELS).contains(&l) => quadtree_levels = Some(l),
+                _ => {
+                    println!(
+                        "Please pass a number of quadtree levels from 1 to {}",
+                        MAX_QUADTREE_LEVELS
+                    );
+                    return Ok(());
+                }
+            },
+            "-n" | "--connectivity" => match args_iter.next().and_then(|n| n.parse().ok()) {
+                Some(n) if [4, 8, 16].contains(&n) => connectivity = Some(n),
+                _ => {
+                    println!("Please pass a connectivity of 4, 8 or 16");
+                    return Ok(());
+                }
+            },
+            _ if file_name.is_none() => file_name = Some(arg),
+            _ => {
+                println!("Too many arguments");
                 return Ok(());
             }
         }
-        _ => {
-            println!("Too many arguments");
+    }
+
+    let file_name = match file_name {
+        Some(f) => f,
+        None => {
+            println!("Please pass a pbf file");
             return Ok(());
         }
-    }
+    };
 
     let coasts;
     if !skip_read_pbf {
         coasts = Coasts::new_from_pbffile(file_name);
-        //coasts.write_to_geojson("coastlines.json");
         coasts.write_to_binfile("coastlines.bin");
     } else {
         coasts = Coasts::new_from_binfile(file_name);
-        //coasts.write_to_geojson("coastlines.json");
+    }
+    if let Some(coastlines_file) = coastlines_file {
+        coasts.write_to_geojson(coastlines_file);
+    }
+
+    if equal_area_node_count.is_some() && bounding_box != GLOBAL_BOUNDING_BOX {
+        println!("Equally distributed nodes always cover the whole globe");
+        return Ok(());
+    }
+    if equal_area_node_count.is_some() && quadtree_levels.is_some() {
+        println!("Equally distributed nodes can't be refined with a quadtree");
+        return Ok(());
+    }
+    if connectivity.is_some() && (equal_area_node_count.is_some() || quadtree_levels.is_some()) {
+        println!("The connectivity can only be set for raster nodes");
+        return Ok(());
+    }
+
+    let mut nodes = match (equal_area_node_count, quadtree_levels) {
+        (Some(node_count), _) => Nodes::new_generate_equally_distributed(node_count),
+        (None, Some(levels)) => {
+            Nodes::new_generate_quadtree(&coasts, columns_count, rows_count, bounding_box, levels)
+        }
+        (None, None) => {
+            Nodes::new_generate_not_equally_distributed(columns_count, rows_count, bounding_box)
+        }
+    };
+    nodes.set_water_flags(&coasts);
+    if let Some(bathymetry_file) = bathymetry_file {
+        nodes.set_depths(&Raster::new_from_ascfile(bathymetry_file));
     }
 
-    let mut nodes = Nodes::new_generate_not_equally_distributed();
-    nodes.set_water_flags(coasts);
+    let rings: Vec<Vec<[f64; 2]>> = coasts
+        .actual_coasts
+        .iter()
+        .map(|c| {
+            c.coordinates
+                .iter()
+                .map(|p| [p.get_lon(), p.get_lat()])
+                .collect()
+        })
+        .collect();
+    let coastline_index =
+        CoastlineIndex::new_from_rings(rings.iter().map(|r| (r.as_slice(), true)));
+    drop(rings);
 
     nodes.write_to_geojson("nodes.json");
     nodes.write_to_binfile("nodes.bin");
     //let nodes = Nodes::new_from_binfile("nodes.bin");
-    let graph = Graph::new_from_nodes(nodes, GRAPH_COLUMNS_COUNT, GRAPH_ROWS_COUNT);
+    let mut graph = if !nodes.neighbors.is_empty() {
+        Graph::new_from_nodes(
+            nodes,
+            0,
+            0,
+            bounding_box,
+            4,
+            &coastline_index,
+            great_circle_edges,
+        )
+    } else {
+        Graph::new_from_nodes(
+            nodes,
+            columns_count,
+            rows_count,
+            bounding_box,
+            connectivity.unwrap_or(4),
+            &coastline_index,
+            great_circle_edges,
+        )
+    };
+    if let Some(sea_areas_file) = sea_areas_file {
+        graph.set_sea_areas(&Zone::new_from_geojson_file(sea_areas_file));
+    }
     graph.write_to_binfile("graph.bin");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_symmetric(neighbors: &[Vec<usize>]) -> bool {
+        (0..neighbors.len()).all(|node| {
+            neighbors[node]
+                .iter()
+                .all(|n| neighbors[*n].contains(&node))
+        })
+    }
+
+    #[test]
+    fn ring_neighbors_with_polar_caps() {
+        let neighbors = Nodes::get_ring_neighbors(&[(0, 1), (1, 4), (5, 1)], 6);
+        assert_eq!(neighbors[0], vec![1, 2, 3, 4]);
+        assert_eq!(neighbors[1], vec![0, 2, 4, 5]);
+        assert_eq!(neighbors[5], vec![1, 2, 3, 4]);
+        assert!(is_symmetric(&neighbors));
+    }
+
+    #[test]
+    fn ring_neighbors_of_rings_with_different_counts() {
+        let neighbors = Nodes::get_ring_neighbors(&[(0, 4), (4, 8)], 12);
+        assert_eq!(neighbors[1], vec![0, 2, 5, 6, 7]);
+        assert_eq!(neighbors[5], vec![0, 1, 4, 6]);
+        assert!(is_symmetric(&neighbors));
+    }
+}
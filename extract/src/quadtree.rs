@@ -0,0 +1,383 @@
+use rayon::prelude::*;
+use route::UNKNOWN_DEPTH;
+use std::collections::HashMap;
+
+use crate::{Coasts, Coordinate, Node, Nodes, FACTOR};
+
+type BoundingBox = (f64, f64, f64, f64); // min lon, min lat, max lon, max lat
+
+// Leaf of the quadtree, column and row are counted at its own level
+#[derive(Clone, Copy)]
+struct Cell {
+    level: u32,
+    column: usize,
+    row: usize,
+}
+
+struct Quadtree {
+    columns_count: usize, // Of the coarse raster
+    rows_count: usize,
+    bounding_box: BoundingBox,
+    levels: u32,
+}
+
+impl Quadtree {
+    fn get_cell_bounding_box(&self, cell: &Cell) -> BoundingBox {
+        let lon_step =
+            (self.bounding_box.2 - self.bounding_box.0) / (self.columns_count << cell.level) as f64;
+        let lat_step =
+            (self.bounding_box.3 - self.bounding_box.1) / (self.rows_count << cell.level) as f64;
+        let min_lon = self.bounding_box.0 + cell.column as f64 * lon_step;
+        let max_lat = self.bounding_box.3 - cell.row as f64 * lat_step;
+        (min_lon, max_lat - lat_step, min_lon + lon_step, max_lat)
+    }
+
+    // Splits cells crossed by any of the coastline segments until the deepest level is reached
+    fn subdivide(&self, cell: Cell, segments: &[(Coordinate, Coordinate)], leaves: &mut Vec<Cell>) {
+        if cell.level == self.levels || segments.is_empty() {
+            leaves.push(cell);
+            return;
+        }
+        for (dc, dr) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let child = Cell {
+                level: cell.level + 1,
+                column: 2 * cell.column + dc,
+                row: 2 * cell.row + dr,
+            };
+            let bounding_box = self.get_cell_bounding_box(&child);
+            let child_segments: Vec<(Coordinate, Coordinate)> = segments
+                .iter()
+                .filter(|(p, q)| segment_intersects_rectangle(p, q, bounding_box))
+                .copied()
+                .collect();
+            self.subdivide(child, &child_segments, leaves);
+        }
+    }
+}
+
+// Segments whose bounding box overlaps the rectangle cross it unless all corners lie on the same side
+fn segment_intersects_rectangle(p: &Coordinate, q: &Coordinate, rect: BoundingBox) -> bool {
+    let (p, q) = ([p.get_lon(), p.get_lat()], [q.get_lon(), q.get_lat()]);
+    if p[0].max(q[0]) < rect.0
+        || p[1].max(q[1]) < rect.1
+        || p[0].min(q[0]) > rect.2
+        || p[1].min(q[1]) > rect.3
+    {
+        return false;
+    }
+    let side = |lon: f64, lat: f64| (q[0] - p[0]) * (lat - p[1]) - (q[1] - p[1]) * (lon - p[0]);
+    let sides = [
+        side(rect.0, rect.1),
+        side(rect.2, rect.1),
+        side(rect.2, rect.3),
+        side(rect.0, rect.3),
+    ];
+    !(sides.iter().all(|s| *s > 0.0) || sides.iter().all(|s| *s < 0.0))
+}
+
+impl Nodes {
+    // Coarse raster whose cells crossed by coastlines are split into four cells up to the given number of
+    // levels. The nodes lie at the cell centers and are connected to all cells sharing a border with them.
+    pub fn new_generate_quadtree(
+        coasts: &Coasts,
+        columns_count: usize,
+        rows_count: usize,
+        bounding_box: BoundingBox,
+        levels: u32,
+    ) -> Nodes {
+        println!(
+            "Generating quadtree nodes ({}x{} in {:?}, {} levels)",
+            columns_count, rows_count, bounding_box, levels
+        );
+        let quadtree = Quadtree {
+            columns_count,
+            rows_count,
+            bounding_box,
+            levels,
+        };
+        let lon_step = (bounding_box.2 - bounding_box.0) / columns_count as f64;
+        let lat_step = (bounding_box.3 - bounding_box.1) / rows_count as f64;
+
+        // Coastline segments of each coarse cell
+        let mut cell_segments = vec![Vec::new(); columns_count * rows_count];
+        for coast in coasts.actual_coasts.iter() {
+            for i in 0..coast.coordinates.len() {
+                let p = coast.coordinates[i];
+                let q = coast.coordinates[(i + 1) % coast.coordinates.len()];
+                // Segments on the border between two cells belong to both
+                let first_column = ((p.get_lon().min(q.get_lon()) - bounding_box.0) / lon_step
+                    - 1.0)
+                    .ceil()
+                    .max(0.0) as usize;
+                let last_column = ((p.get_lon().max(q.get_lon()) - bounding_box.0) / lon_step)
+                    .floor()
+                    .min(columns_count as f64 - 1.0);
+                let first_row = ((bounding_box.3 - p.get_lat().max(q.get_lat())) / lat_step - 1.0)
+                    .ceil()
+                    .max(0.0) as usize;
+                let last_row = ((bounding_box.3 - p.get_lat().min(q.get_lat())) / lat_step)
+                    .floor()
+                    .min(rows_count as f64 - 1.0);
+                if last_column < 0.0 || last_row < 0.0 {
+                    continue;
+                }
+                for row in first_row..=last_row as usize {
+                    for column in first_column..=last_column as usize {
+                        cell_segments[row * columns_count + column].push((p, q));
+                    }
+                }
+            }
+        }
+
+        let leaves: Vec<Cell> = cell_segments
+            .par_iter()
+            .enumerate()
+            .flat_map_iter(|(i, segments)| {
+                let mut leaves = Vec::new();
+                let cell = Cell {
+                    level: 0,
+                    column: i % columns_count,
+                    row: i / columns_count,
+                };
+                quadtree.subdivide(cell, segments, &mut leaves);
+                leaves
+            })
+            .collect();
+        drop(cell_segments);
+        println!("Generated {} cells", leaves.len());
+
+        let nodes = leaves
+            .iter()
+            .map(|cell| {
+                let (min_lon, min_lat, max_lon, max_lat) = quadtree.get_cell_bounding_box(cell);
+                Node {
+                    coordinate: Coordinate {
+                        lon: ((min_lon + max_lon) / 2.0 * FACTOR) as i32,
+                        lat: ((min_lat + max_lat) / 2.0 * FACTOR) as i32,
+                    },
+                    is_water: true,
+                    depth: UNKNOWN_DEPTH,
+                }
+            })
+            .collect();
+        let neighbors = quadtree.get_neighbors(&leaves);
+        Nodes { nodes, neighbors }
+    }
+}
+
+impl Quadtree {
+    // Cells sharing a border, found by walking along the borders in steps of the deepest level
+    fn get_neighbors(&self, leaves: &[Cell]) -> Vec<Vec<usize>> {
+        let leaf_indices: HashMap<(u32, usize, usize), usize> = leaves
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| ((cell.level, cell.column, cell.row), i))
+            .collect();
+        let fine_columns_count = self.columns_count << self.levels;
+        let fine_rows_count = self.rows_count << self.levels;
+        let is_global = self.bounding_box.2 - self.bounding_box.0 >= 360.0;
+
+        // Leaf containing the cell of the deepest level and the size of the leaf in those cells
+        let find_leaf = |fine_column: usize, fine_row: usize| {
+            (0..=self.levels)
+                .find_map(|level| {
+                    let shift = self.levels - level;
+                    leaf_indices
+                        .get(&(level, fine_column >> shift, fine_row >> shift))
+                        .map(|i| (*i, 1 << shift))
+                })
+                .unwrap()
+        };
+
+        leaves
+            .par_iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                let shift = self.levels - cell.level;
+                let first_column = cell.column << shift;
+                let first_row = cell.row << shift;
+                let size = 1 << shift;
+
+                let mut neighbors = Vec::new();
+                // Columns left and right of the cell, which wrap around on global rasters
+                for column in [first_column as isize - 1, (first_column + size) as isize] {
+                    let column = if is_global {
+                        column.rem_euclid(fine_columns_count as isize)
+                    } else if column < 0 || column >= fine_columns_count as isize {
+                        continue;
+                    } else {
+                        column
+                    };
+                    let mut row = first_row;
+                    while row < first_row + size {
+                        let (neighbor, neighbor_size) = find_leaf(column as usize, row);
+                        neighbors.push(neighbor);
+                        row = (row / neighbor_size + 1) * neighbor_size;
+                    }
+                }
+                // Rows above and below the cell
+                for row in [first_row as isize - 1, (first_row + size) as isize] {
+                    if row < 0 || row >= fine_rows_count as isize {
+                        continue;
+                    }
+                    let mut column = first_column;
+                    while column < first_column + size {
+                        let (neighbor, neighbor_size) = find_leaf(column, row as usize);
+                        neighbors.push(neighbor);
+                        column = (column / neighbor_size + 1) * neighbor_size;
+                    }
+                }
+
+                neighbors.sort_unstable();
+                neighbors.dedup();
+                neighbors.retain(|n| *n != i);
+                neighbors
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Coast;
+    use route::zones::Zone;
+    use route::{Edge, Graph, Restrictions};
+
+    fn coordinate(lon: f64, lat: f64) -> Coordinate {
+        Coordinate {
+            lon: (lon * FACTOR) as i32,
+            lat: (lat * FACTOR) as i32,
+        }
+    }
+
+    // Quadtree around a square island in the top right coarse cell, the other coarse cells stay unrefined
+    fn create_graph() -> Graph {
+        let island = [(31.0, 31.0), (38.0, 31.0), (38.0, 38.0), (31.0, 38.0)];
+        let coasts = Coasts {
+            actual_coasts: vec![Coast {
+                coordinates: island
+                    .iter()
+                    .map(|(lon, lat)| coordinate(*lon, *lat))
+                    .collect(),
+                leftmost: coordinate(31.0, 0.0).lon,
+                rightmost: coordinate(38.0, 0.0).lon,
+            }],
+        };
+        let nodes = Nodes::new_generate_quadtree(&coasts, 4, 4, (0.0, 0.0, 40.0, 40.0), 5);
+
+        let mut graph = Graph {
+            bounding_box: (0.0, 0.0, 40.0, 40.0),
+            coordinates: nodes
+                .nodes
+                .iter()
+                .map(|n| [n.coordinate.get_lon(), n.coordinate.get_lat()])
+                .collect(),
+            ..Default::default()
+        };
+        for neighbors in nodes.neighbors.iter() {
+            graph.offsets.push((graph.edges.len() as u32, None));
+            graph.edges.extend(neighbors.iter().map(|n| Edge {
+                destination: *n as u32,
+                distance: 1,
+            }));
+        }
+        graph.offsets.push((graph.edges.len() as u32, None));
+        graph.build_spatial_index();
+        graph
+    }
+
+    fn find_node(graph: &Graph, lon: f64, lat: f64) -> usize {
+        graph
+            .coordinates
+            .iter()
+            .position(|c| (c[0] - lon).abs() < 1e-6 && (c[1] - lat).abs() < 1e-6)
+            .unwrap()
+    }
+
+    // The edge between the coarse cells at 5,5 and 15,5 crosses a zone far smaller than the cells
+    #[test]
+    fn restrictions_cover_coarse_edges() {
+        let graph = create_graph();
+        let (first, second) = (find_node(&graph, 5.0, 5.0), find_node(&graph, 15.0, 5.0));
+        let edge = (graph.offsets[first].0..graph.offsets[first + 1].0)
+            .find(|e| graph.edges[*e as usize].destination as usize == second)
+            .unwrap() as usize;
+        let zones = Zone::new_from_geojson(
+            r#"{"type": "Feature", "properties": {"cost_factor": 2}, "geometry": {"type": "Polygon",
+            "coordinates": [[[9.5, 4.5], [10.5, 4.5], [10.5, 5.5], [9.5, 5.5], [9.5, 4.5]]]}}"#,
+        )
+        .unwrap();
+
+        let mut restrictions = Restrictions::default();
+        graph.block_zones(&zones, &mut restrictions);
+        assert!(restrictions.is_edge_blocked(edge));
+
+        let mut restrictions = Restrictions::default();
+        graph.penalize_zones(&zones, &mut restrictions);
+        assert_ne!(restrictions.node_zones[first], 0);
+        assert_ne!(restrictions.node_zones[second], 0);
+
+        let mut restrictions = Restrictions::default();
+        graph.restrict_to_corridor(&[[10.0, 1.0], [10.0, 9.0]], 20000.0, &mut restrictions);
+        assert!(!restrictions.is_node_blocked(first));
+        assert!(!restrictions.is_node_blocked(second));
+    }
+
+    fn create_quadtree(bounding_box: BoundingBox) -> (Quadtree, Vec<Cell>) {
+        let quadtree = Quadtree {
+            columns_count: 2,
+            rows_count: 1,
+            bounding_box,
+            levels: 1,
+        };
+        // The left coarse cell stays, the right one is split into four cells
+        let leaves = vec![
+            Cell {
+                level: 0,
+                column: 0,
+                row: 0,
+            },
+            Cell {
+                level: 1,
+                column: 2,
+                row: 0,
+            },
+            Cell {
+                level: 1,
+                column: 3,
+                row: 0,
+            },
+            Cell {
+                level: 1,
+                column: 2,
+                row: 1,
+            },
+            Cell {
+                level: 1,
+                column: 3,
+                row: 1,
+            },
+        ];
+        (quadtree, leaves)
+    }
+
+    #[test]
+    fn neighbors_of_different_levels() {
+        let (quadtree, leaves) = create_quadtree((0.0, 0.0, 20.0, 10.0));
+        let neighbors = quadtree.get_neighbors(&leaves);
+        assert_eq!(neighbors[0], vec![1, 3]);
+        assert_eq!(neighbors[1], vec![0, 2, 3]);
+        assert_eq!(neighbors[2], vec![1, 4]);
+        assert_eq!(neighbors[3], vec![0, 1, 4]);
+        assert_eq!(neighbors[4], vec![2, 3]);
+    }
+
+    #[test]
+    fn neighbors_wrap_around_on_global_quadtrees() {
+        let (quadtree, leaves) = create_quadtree((-180.0, -90.0, 180.0, 90.0));
+        let neighbors = quadtree.get_neighbors(&leaves);
+        assert_eq!(neighbors[0], vec![1, 2, 3, 4]);
+        assert_eq!(neighbors[2], vec![0, 1, 4]);
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::{env, fs};
+
+use route::Graph;
+use serde_json::{json, Value};
+
+const DEFAULT_OUTPUT_FILE: &str = "graph_diff.json";
+
+#[derive(Default)]
+struct NodeDiff {
+    became_water: bool,
+    became_land: bool,
+    added_edges: usize,
+    removed_edges: usize,
+    reweighted_edges: usize,
+}
+
+impl NodeDiff {
+    fn is_changed(&self) -> bool {
+        self.became_water
+            || self.became_land
+            || self.added_edges > 0
+            || self.removed_edges > 0
+            || self.reweighted_edges > 0
+    }
+
+    fn get_change(&self) -> &'static str {
+        if self.became_water {
+            "water"
+        } else if self.became_land {
+            "land"
+        } else {
+            "edges"
+        }
+    }
+}
+
+// Outgoing edges of the node by destination, parallel edges keep the shortest distance
+fn get_edges(graph: &Graph, node: usize) -> HashMap<u32, u32> {
+    let mut edges = HashMap::new();
+    for edge in
+        graph.edges[graph.offsets[node].0 as usize..graph.offsets[node + 1].0 as usize].iter()
+    {
+        let distance = edges.entry(edge.destination).or_insert(edge.distance);
+        *distance = (*distance).min(edge.distance);
+    }
+    edges
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    if args.len() < 3 {
+        println!("Required: <old graph binary file> <new graph binary file> [geojson output file]");
+        return;
+    }
+    let output_file = args.get(3).map_or(DEFAULT_OUTPUT_FILE, |f| f.as_str());
+
+    let old_graph = Graph::new_from_binfile(&args[1]);
+    let new_graph = Graph::new_from_binfile(&args[2]);
+    if !old_graph.is_raster() || !new_graph.is_raster() {
+        println!("Only raster graphs can be compared");
+        return;
+    }
+    if old_graph.raster_columns_count != new_graph.raster_columns_count
+        || old_graph.raster_rows_count != new_graph.raster_rows_count
+        || old_graph.bounding_box != new_graph.bounding_box
+    {
+        println!(
+            "The graphs have different resolutions ({}x{} in {:?} and {}x{} in {:?})",
+            old_graph.raster_columns_count,
+            old_graph.raster_rows_count,
+            old_graph.bounding_box,
+            new_graph.raster_columns_count,
+            new_graph.raster_rows_count,
+            new_graph.bounding_box
+        );
+        return;
+    }
+
+    let node_count = old_graph.raster_columns_count * old_graph.raster_rows_count;
+    let mut diffs = Vec::new();
+    for node in 0..node_count {
+        let old_edges = get_edges(&old_graph, node);
+        let new_edges = get_edges(&new_graph, node);
+        let mut diff = NodeDiff {
+            became_water: old_edges.is_empty() && !new_edges.is_empty(),
+            became_land: !old_edges.is_empty() && new_edges.is_empty(),
+            ..Default::default()
+        };
+        for (destination, distance) in old_edges.iter() {
+            match new_edges.get(destination) {
+                Some(new_distance) if new_distance != distance => diff.reweighted_edges += 1,
+                Some(_) => {}
+                None => diff.removed_edges += 1,
+            }
+        }
+        diff.added_edges = new_edges
+            .keys()
+            .filter(|d| !old_edges.contains_key(d))
+            .count();
+        if diff.is_changed() {
+            diffs.push((node, diff));
+        }
+    }
+
+    println!();
+    println!(
+        "Nodes which became water: {}",
+        diffs.iter().filter(|(_, d)| d.became_water).count()
+    );
+    println!(
+        "Nodes which became land: {}",
+        diffs.iter().filter(|(_, d)| d.became_land).count()
+    );
+    println!(
+        "Added edges: {}",
+        diffs.iter().map(|(_, d)| d.added_edges).sum::<usize>()
+    );
+    println!(
+        "Removed edges: {}",
+        diffs.iter().map(|(_, d)| d.removed_edges).sum::<usize>()
+    );
+    println!(
+        "Reweighted edges: {}",
+        diffs.iter().map(|(_, d)| d.reweighted_edges).sum::<usize>()
+    );
+    println!(
+        "Shortcut rectangles: {} and {}",
+        old_graph.shortcut_rectangles.len(),
+        new_graph.shortcut_rectangles.len()
+    );
+
+    // Every changed node as the raster cell around it
+    let lon_step = new_graph.get_lon_step();
+    let lat_step = new_graph.get_lat_step();
+    let features: Vec<Value> = diffs
+        .iter()
+        .map(|(node, diff)| {
+            let lon = new_graph.get_lon(*node);
+            let lat = new_graph.get_lat(*node);
+            let (west, east) = (lon - lon_step / 2.0, lon + lon_step / 2.0);
+            let (south, north) = (
+                (lat - lat_step / 2.0).max(-90.0),
+                (lat + lat_step / 2.0).min(90.0),
+            );
+            json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Polygon",
+                    "coordinates": [[[west, south], [east, south], [east, north], [west, north], [west, south]]],
+                },
+                "properties": {
+                    "node": node,
+                    "change": diff.get_change(),
+                    "added_edges": diff.added_edges,
+                    "removed_edges": diff.removed_edges,
+                    "reweighted_edges": diff.reweighted_edges,
+                },
+            })
+        })
+        .collect();
+    let geojson = json!({
+        "type": "FeatureCollection",
+        "features": features,
+    });
+    fs::write(output_file, geojson.to_string()).unwrap();
+    println!("{} changed cells written to {}", diffs.len(), output_file);
+}
@@ -0,0 +1,210 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::{env, fs, mem};
+
+use route::components::NO_COMPONENT;
+use route::{Edge, Graph};
+
+const LATITUDE_BAND_SIZE: usize = 10; // Degrees
+const LISTED_COMPONENTS_COUNT: usize = 10;
+
+type BoundingBox = (f64, f64, f64, f64); // min lon, min lat, max lon, max lat
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    if args.len() < 2 {
+        println!("Required: <Graph binary file>");
+        return;
+    }
+
+    let file_size = fs::metadata(&args[1]).unwrap().len();
+    let graph = Graph::new_from_binfile(&args[1]);
+    let node_count = graph.offsets.len() - 1;
+    let water_nodes: Vec<usize> = (0..node_count)
+        .filter(|n| graph.offsets[*n] != graph.offsets[n + 1])
+        .collect();
+
+    println!();
+    if graph.is_raster() {
+        println!(
+            "Raster: {} columns x {} rows, {}-neighbor connectivity",
+            graph.raster_columns_count, graph.raster_rows_count, graph.connectivity
+        );
+    } else {
+        println!(
+            "Explicit node coordinates, mean spacing {:.3} degrees",
+            graph.get_lat_step()
+        );
+    }
+    println!(
+        "Bounding box: lon {} to {}, lat {} to {}{}",
+        graph.bounding_box.0,
+        graph.bounding_box.2,
+        graph.bounding_box.1,
+        graph.bounding_box.3,
+        if graph.is_global() { " (global)" } else { "" }
+    );
+    println!("Nodes: {}", node_count);
+    println!(
+        "Water nodes: {} ({:.2}%)",
+        water_nodes.len(),
+        water_nodes.len() as f64 / node_count as f64 * 100.0
+    );
+    println!("Edges: {}", graph.edges.len());
+
+    // Edges which don't connect raster neighbors are shortcut or canal edges
+    let mut degrees = BTreeMap::new();
+    let mut added_edges_count = 0;
+    for node in water_nodes.iter() {
+        let edge_range = graph.offsets[*node].0 as usize..graph.offsets[node + 1].0 as usize;
+        *degrees.entry(edge_range.len()).or_insert(0) += 1;
+        added_edges_count += graph.edges[edge_range]
+            .iter()
+            .filter(|e| !graph.is_raster_neighbor(*node, e.destination as usize))
+            .count();
+    }
+    println!();
+    println!("Degree distribution of the water nodes:");
+    for (degree, count) in degrees.iter() {
+        println!("  {:>3} edges: {} nodes", degree, count);
+    }
+
+    println!();
+    println!("Shortcut rectangles: {}", graph.shortcut_rectangles.len());
+    println!(
+        "Nodes inside of shortcut rectangles: {}",
+        graph.offsets[..node_count]
+            .iter()
+            .filter(|o| o.1.is_some())
+            .count()
+    );
+    println!("Added edges (shortcuts): {}", added_edges_count);
+
+    print_components(&graph, &water_nodes);
+    print_edge_lengths(&graph, &water_nodes);
+
+    println!();
+    println!("Memory footprint:");
+    let sizes = [
+        (
+            "offsets",
+            graph.offsets.len() * mem::size_of::<(u32, Option<usize>)>(),
+        ),
+        ("edges", graph.edges.len() * mem::size_of::<Edge>()),
+        ("depths", graph.depths.len() * mem::size_of::<i16>()),
+        ("sea areas", graph.sea_areas.len() * mem::size_of::<u16>()),
+        ("components", graph.components.len() * mem::size_of::<u32>()),
+        (
+            "coordinates",
+            graph.coordinates.len() * mem::size_of::<[f64; 2]>(),
+        ),
+    ];
+    for (name, size) in sizes.iter() {
+        println!("  {:<11} {:>10.1} MB", name, *size as f64 / 1_000_000.0);
+    }
+    println!(
+        "  {:<11} {:>10.1} MB",
+        "total",
+        sizes.iter().map(|(_, s)| s).sum::<usize>() as f64 / 1_000_000.0
+    );
+    println!(
+        "  {:<11} {:>10.1} MB",
+        "file",
+        file_size as f64 / 1_000_000.0
+    );
+    if graph.depths.is_empty() {
+        println!("The graph has no depths");
+    }
+    if graph.sea_area_names.is_empty() {
+        println!("The graph has no sea areas");
+    } else {
+        println!("Sea areas: {}", graph.sea_area_names.len());
+    }
+}
+
+fn print_components(graph: &Graph, water_nodes: &[usize]) {
+    // Node count and bounding box of each component
+    let mut components: BTreeMap<u32, (usize, BoundingBox)> = BTreeMap::new();
+    for node in water_nodes.iter() {
+        let component = graph.components[*node];
+        if component == NO_COMPONENT {
+            continue;
+        }
+        let (lon, lat) = (graph.get_lon(*node), graph.get_lat(*node));
+        let entry = components
+            .entry(component)
+            .or_insert((0, (f64::MAX, f64::MAX, f64::MIN, f64::MIN)));
+        entry.0 += 1;
+        entry.1 .0 = entry.1 .0.min(lon);
+        entry.1 .1 = entry.1 .1.min(lat);
+        entry.1 .2 = entry.1 .2.max(lon);
+        entry.1 .3 = entry.1 .3.max(lat);
+    }
+    let mut components: Vec<(u32, (usize, BoundingBox))> = components.into_iter().collect();
+    components.sort_by_key(|c| Reverse(c.1 .0));
+
+    println!();
+    println!(
+        "Connected components: {} ({} with a single node)",
+        components.len(),
+        components.iter().filter(|c| c.1 .0 == 1).count()
+    );
+    for (component, (size, bounding_box)) in components.iter().take(LISTED_COMPONENTS_COUNT) {
+        println!(
+            "  {:>6}: {:>8} nodes ({:.2}%), lon {:.2} to {:.2}, lat {:.2} to {:.2}",
+            component,
+            size,
+            *size as f64 / water_nodes.len() as f64 * 100.0,
+            bounding_box.0,
+            bounding_box.2,
+            bounding_box.1,
+            bounding_box.3
+        );
+    }
+    if components.len() > LISTED_COMPONENTS_COUNT {
+        println!(
+            "  ... {} smaller components",
+            components.len() - LISTED_COMPONENTS_COUNT
+        );
+    }
+}
+
+// Lengths of the edges between raster neighbors grouped by the latitude of their source node
+fn print_edge_lengths(graph: &Graph, water_nodes: &[usize]) {
+    let band_count = 180 / LATITUDE_BAND_SIZE;
+    // Count, min, sum and max length in meters for each band
+    let mut bands = vec![(0, u32::MAX, 0u64, 0); band_count];
+    for node in water_nodes.iter() {
+        let band = (((90.0 - graph.get_lat(*node)) / LATITUDE_BAND_SIZE as f64) as usize)
+            .min(band_count - 1);
+        for edge in graph.edges[graph.offsets[*node].0 as usize..graph.offsets[node + 1].0 as usize]
+            .iter()
+            .filter(|e| graph.is_raster_neighbor(*node, e.destination as usize))
+        {
+            let entry = &mut bands[band];
+            entry.0 += 1;
+            entry.1 = entry.1.min(edge.distance);
+            entry.2 += edge.distance as u64;
+            entry.3 = entry.3.max(edge.distance);
+        }
+    }
+
+    println!();
+    println!("Edge lengths per latitude band (km):");
+    for (i, (count, min, sum, max)) in bands.iter().enumerate() {
+        if *count == 0 {
+            continue;
+        }
+        let max_lat = 90 - (i * LATITUDE_BAND_SIZE) as i32;
+        println!(
+            "  {:>3} to {:>3}: {:>8} edges, min {:>7.2}, mean {:>7.2}, max {:>7.2}",
+            max_lat - LATITUDE_BAND_SIZE as i32,
+            max_lat,
+            count,
+            *min as f64 / 1000.0,
+            *sum as f64 / *count as f64 / 1000.0,
+            *max as f64 / 1000.0
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use std::fs;
+
+use crate::{Edge, Graph, QueryOptions, Restrictions, METERS_PER_NAUTICAL_MILE};
+
+// Artificial connection like the Suez or Panama Canal which is too narrow for the raster of the graph
+#[derive(serde::Deserialize, Clone)]
+pub struct Canal {
+    pub name: String,
+    pub entry: [f64; 2],   // lon, lat
+    pub exit: [f64; 2],    // lon, lat
+    pub distance: f64,     // Nautical miles
+    pub transit_time: f64, // Hours including waiting times
+    #[serde(default)]
+    pub toll: f64,
+    // Vessel size limits in meters, None if unrestricted
+    pub max_beam: Option<f64>,
+    pub max_draft: Option<f64>,
+    pub max_air_draft: Option<f64>,
+    #[serde(skip)]
+    pub nodes: (usize, usize), // Nearest nodes of entry and exit
+    #[serde(skip)]
+    pub edges: Vec<usize>, // Edges for both directions
+}
+
+#[derive(serde::Serialize)]
+pub struct CanalTransit {
+    pub name: String,
+    pub distance: f64,     // Nautical miles
+    pub transit_time: f64, // Hours
+    pub toll: f64,
+}
+
+impl Canal {
+    pub fn new_from_jsonfile(filename: &str) -> Vec<Self> {
+        println!("Reading canals from json file: {}", filename);
+        let canals: Vec<Self> =
+            serde_json::from_str(&fs::read_to_string(filename).unwrap()).unwrap();
+        println!("Read {} canals", canals.len());
+        canals
+    }
+
+    // Whether the vessel of the query may use the canal
+    pub fn is_allowed(&self, options: &QueryOptions) -> bool {
+        let exceeds = |size: Option<f64>, limit: Option<f64>| match (size, limit) {
+            (Some(size), Some(limit)) => size > limit,
+            _ => false,
+        };
+        !options.forbidden_canals.contains(&self.name)
+            && !exceeds(options.beam, self.max_beam)
+            && !exceeds(options.draft, self.max_draft)
+            && !exceeds(options.air_draft, self.max_air_draft)
+    }
+
+    pub fn get_transit(&self) -> CanalTransit {
+        CanalTransit {
+            name: self.name.clone(),
+            distance: self.distance,
+            transit_time: self.transit_time,
+            toll: self.toll,
+        }
+    }
+}
+
+impl Graph {
+    // Connects the nearest nodes of entry and exit of each canal with edges in both directions
+    pub fn add_canals(&mut self, canals: Vec<Canal>) {
+        assert!(self.canals.is_empty(), "Canals can only be added once");
+        let mut added_canals = Vec::new();
+        for mut canal in canals {
+            let entry = self.find_nearest_node(canal.entry[0], canal.entry[1]);
+            let exit = self.find_nearest_node(canal.exit[0], canal.exit[1]);
+            match (entry, exit) {
+                (Some(entry), Some(exit))
+                    if entry != exit
+                        && !self.is_raster_neighbor(entry, exit)
+                        && self.offsets[entry].1.is_none()
+                        && self.offsets[exit].1.is_none() =>
+                {
+                    canal.nodes = (entry, exit);
+                    added_canals.push(canal);
+                }
+                _ => println!(
+                    "Skipping canal {} as its entry and exit don't lie on separate, not neighboring nodes outside of shortcut rectangles",
+                    canal.name
+                ),
+            }
+        }
+
+        let mut offsets = Vec::with_capacity(self.offsets.len());
+        let mut edges = Vec::with_capacity(self.edges.len() + 2 * added_canals.len());
+        for i in 0..self.offsets.len() - 1 {
+            offsets.push((edges.len() as u32, self.offsets[i].1));
+            edges.extend_from_slice(
+                &self.edges[self.offsets[i].0 as usize..self.offsets[i + 1].0 as usize],
+            );
+            for canal in added_canals.iter_mut() {
+                let dest = if canal.nodes.0 == i {
+                    canal.nodes.1
+                } else if canal.nodes.1 == i {
+                    canal.nodes.0
+                } else {
+                    continue;
+                };
+                canal.edges.push(edges.len());
+                edges.push(Edge {
+                    destination: dest as u32,
+                    distance: self.get_canal_weight(canal),
+                });
+            }
+        }
+        offsets.push((edges.len() as u32, None));
+        println!("Added {} canals", added_canals.len());
+
+        self.offsets = offsets;
+        self.edges = edges;
+        self.canal_edges = added_canals
+            .iter()
+            .enumerate()
+            .flat_map(|(c, canal)| canal.edges.iter().map(move |edge| (*edge, c)))
+            .collect();
+        self.canals = added_canals;
+        // Canals can connect basins which were separated before
+        self.label_components();
+    }
+
+    // Transit distance or time scaled like the other edges, but never below the heuristic of the
+    // A* algorithms to keep them admissible
+    fn get_canal_weight(&self, canal: &Canal) -> u32 {
+        let weight = match &self.cost_model {
+            Some(cost_model) => canal.transit_time * 3600.0 * cost_model.reference_speed,
+            None => canal.distance * METERS_PER_NAUTICAL_MILE,
+        } as u32;
+        let (entry, exit) = canal.nodes;
+        weight.max(self.heuristic_distance(
+            self.get_lon(entry),
+            self.get_lat(entry),
+            self.get_lon(exit),
+            self.get_lat(exit),
+        ))
+    }
+
+    pub fn get_canal(&self, edge: usize) -> Option<&Canal> {
+        self.canal_edges.get(&edge).map(|c| &self.canals[*c])
+    }
+
+    pub fn block_canals(&self, options: &QueryOptions, restrictions: &mut Restrictions) {
+        for canal in self.canals.iter().filter(|c| !c.is_allowed(options)) {
+            for edge in canal.edges.iter() {
+                restrictions.block_edge(*edge, self.edges.len());
+            }
+        }
+    }
+
+    // Canals passed by the path, which is stored from end to start
+    pub fn get_canal_transits(&self, path: &[usize]) -> Vec<&Canal> {
+        let mut transits = Vec::new();
+        for nodes in path.windows(2).rev() {
+            if let Some(canal) = self
+                .canals
+                .iter()
+                .find(|c| c.nodes == (nodes[1], nodes[0]) || c.nodes == (nodes[0], nodes[1]))
+            {
+                transits.push(canal);
+            }
+        }
+        transits
+    }
+}
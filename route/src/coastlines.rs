@@ -0,0 +1,373 @@
+use crate::zones::{segments_intersect, Zone};
+use crate::{Graph, Restrictions};
+
+// Larger first or last miles are connected with a straight line to avoid slow queries
+const MAX_VISIBILITY_VERTICES: usize = 500;
+const MAX_VISIBILITY_SEGMENTS: usize = 2000;
+// Distance in degrees by which coastline vertices are moved into the water, so that paths can pass them
+const VERTEX_OFFSET: f64 = 0.0001;
+// Size of the cells of the coastline index in degrees
+const CELL_SIZE: f64 = 1.0;
+const COLUMNS_COUNT: usize = (360.0 / CELL_SIZE) as usize;
+const ROWS_COUNT: usize = (180.0 / CELL_SIZE) as usize;
+// Length in degrees of the straight parts approximating great circle segments
+const GREAT_CIRCLE_STEP: f64 = 0.1;
+
+pub struct CoastlineSegment {
+    pub previous: [f64; 2], // Start of the previous segment of the ring
+    pub start: [f64; 2],
+    pub end: [f64; 2],
+    pub is_land_left: bool,
+}
+
+// Segments of the coastline polygons bucketed into the cells overlapped by their bounding boxes, rows start
+// at lat -90. Also used by extract to drop edges crossing the coastlines.
+#[derive(Default)]
+pub struct CoastlineIndex {
+    pub segments: Vec<CoastlineSegment>,
+    cells: Vec<Vec<u32>>,
+}
+
+fn get_column(lon: f64) -> isize {
+    ((lon + 180.0) / CELL_SIZE).floor() as isize
+}
+
+fn get_row(lat: f64) -> usize {
+    ((lat + 90.0) / CELL_SIZE)
+        .floor()
+        .clamp(0.0, ROWS_COUNT as f64 - 1.0) as usize
+}
+
+impl CoastlineIndex {
+    pub fn new(coastlines: &[Zone]) -> Self {
+        Self::new_from_rings(
+            coastlines
+                .iter()
+                .flat_map(|c| c.polygons.iter())
+                .flat_map(|p| p.rings.iter().enumerate())
+                .map(|(r, ring)| (ring.as_slice(), r == 0)),
+        )
+    }
+
+    // Rings of the land polygons and whether they are outer rings or holes
+    pub fn new_from_rings<'a>(rings: impl Iterator<Item = (&'a [[f64; 2]], bool)>) -> Self {
+        println!("Indexing the coastline segments");
+        let mut index = CoastlineIndex {
+            segments: Vec::new(),
+            cells: vec![Vec::new(); COLUMNS_COUNT * ROWS_COUNT],
+        };
+        for (ring, is_outer) in rings {
+            // Outer rings have the land on their left if they are counterclockwise, holes on their right
+            let area: f64 = (0..ring.len())
+                .map(|i| {
+                    let next = ring[(i + 1) % ring.len()];
+                    ring[i][0] * next[1] - next[0] * ring[i][1]
+                })
+                .sum();
+            let is_land_left = (area > 0.0) == is_outer;
+
+            for i in 0..ring.len() {
+                let start = ring[i];
+                let end = ring[(i + 1) % ring.len()];
+                if start == end {
+                    continue;
+                }
+                let first_column =
+                    get_column(start[0].min(end[0])).clamp(0, COLUMNS_COUNT as isize - 1);
+                let last_column =
+                    get_column(start[0].max(end[0])).clamp(0, COLUMNS_COUNT as isize - 1);
+                for row in get_row(start[1].min(end[1]))..=get_row(start[1].max(end[1])) {
+                    for column in first_column..=last_column {
+                        index.cells[row * COLUMNS_COUNT + column as usize]
+                            .push(index.segments.len() as u32);
+                    }
+                }
+                index.segments.push(CoastlineSegment {
+                    previous: ring[(i + ring.len() - 1) % ring.len()],
+                    start,
+                    end,
+                    is_land_left,
+                });
+            }
+        }
+        println!("Indexed {} coastline segments", index.segments.len());
+        index
+    }
+
+    // Segments of all cells overlapping the bounding box, each segment once
+    pub fn get_segments_in_bounding_box(&self, bounding_box: (f64, f64, f64, f64)) -> Vec<usize> {
+        if self.segments.is_empty() {
+            return Vec::new();
+        }
+        let first_column = get_column(bounding_box.0).clamp(0, COLUMNS_COUNT as isize - 1);
+        let last_column = get_column(bounding_box.2).clamp(0, COLUMNS_COUNT as isize - 1);
+        let mut segments = Vec::new();
+        for row in get_row(bounding_box.1)..=get_row(bounding_box.3) {
+            for column in first_column..=last_column {
+                segments.extend(
+                    self.cells[row * COLUMNS_COUNT + column as usize]
+                        .iter()
+                        .map(|s| *s as usize),
+                );
+            }
+        }
+        segments.sort_unstable();
+        segments.dedup();
+        segments
+    }
+
+    pub fn crosses_segment(&self, p: [f64; 2], q: [f64; 2]) -> bool {
+        if self.segments.is_empty() {
+            return false;
+        }
+        // Segments across the antimeridian continue beyond it, the coastline segments are shifted next to them
+        let mut q = q;
+        if q[0] - p[0] > 180.0 {
+            q[0] -= 360.0;
+        } else if q[0] - p[0] < -180.0 {
+            q[0] += 360.0;
+        }
+        let first_column = get_column(p[0].min(q[0]));
+        let last_column = get_column(p[0].max(q[0]));
+        for row in get_row(p[1].min(q[1]))..=get_row(p[1].max(q[1])) {
+            for column in first_column..=last_column {
+                let cell_column = column.rem_euclid(COLUMNS_COUNT as isize);
+                let shift = (column - cell_column) as f64 * CELL_SIZE;
+                for s in self.cells[row * COLUMNS_COUNT + cell_column as usize].iter() {
+                    let segment = &self.segments[*s as usize];
+                    if segments_intersect(
+                        p,
+                        q,
+                        [segment.start[0] + shift, segment.start[1]],
+                        [segment.end[0] + shift, segment.end[1]],
+                    ) {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    // Checks the great circle from p to q instead of the straight line in lon and lat
+    pub fn crosses_great_circle(&self, p: [f64; 2], q: [f64; 2]) -> bool {
+        let mut q = q;
+        if q[0] - p[0] > 180.0 {
+            q[0] -= 360.0;
+        } else if q[0] - p[0] < -180.0 {
+            q[0] += 360.0;
+        }
+        get_great_circle_points(p, q)
+            .windows(2)
+            .any(|w| self.crosses_segment(w[0], w[1]))
+    }
+}
+
+// Points along the great circle from p to q, at most GREAT_CIRCLE_STEP degrees apart. The longitudes
+// continue from p instead of jumping at the antimeridian.
+fn get_great_circle_points(p: [f64; 2], q: [f64; 2]) -> Vec<[f64; 2]> {
+    let to_vector = |[lon, lat]: [f64; 2]| {
+        let (lon, lat) = (lon.to_radians(), lat.to_radians());
+        [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+    };
+    let (u, v) = (to_vector(p), to_vector(q));
+    let angle = (u[0] * v[0] + u[1] * v[1] + u[2] * v[2])
+        .clamp(-1.0, 1.0)
+        .acos();
+    let parts = (angle.to_degrees() / GREAT_CIRCLE_STEP).ceil() as usize;
+    if parts <= 1 {
+        return vec![p, q];
+    }
+
+    let mut points = vec![p];
+    for k in 1..parts {
+        let t = k as f64 / parts as f64;
+        let a = ((1.0 - t) * angle).sin() / angle.sin();
+        let b = (t * angle).sin() / angle.sin();
+        let w = [
+            a * u[0] + b * v[0],
+            a * u[1] + b * v[1],
+            a * u[2] + b * v[2],
+        ];
+        let lat = w[2].clamp(-1.0, 1.0).asin().to_degrees();
+        let mut lon = w[1].atan2(w[0]).to_degrees();
+        let previous_lon = points.last().unwrap()[0];
+        lon += 360.0 * ((previous_lon - lon) / 360.0).round();
+        points.push([lon, lat]);
+    }
+    points.push(q);
+    points
+}
+
+impl Graph {
+    pub fn crosses_coastline(&self, lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> bool {
+        self.coastlines.crosses_segment([lon1, lat1], [lon2, lat2])
+    }
+
+    // Land safe connection from a position to the grid. Returns the node which is reached and the
+    // positions from the given one to the node, which is the straight line if it doesn't cross the
+    // coastlines. Otherwise the shortest path through a visibility graph of the nearby coastline
+    // vertices to any water node around the position is used.
+    pub fn find_coastal_leg(
+        &self,
+        lon: f64,
+        lat: f64,
+        node: usize,
+        restrictions: &Restrictions,
+    ) -> (usize, Vec<[f64; 2]>) {
+        let node_position = [self.get_lon(node), self.get_lat(node)];
+        let direct = (node, vec![[lon, lat], node_position]);
+        if !self.crosses_coastline(lon, lat, node_position[0], node_position[1]) {
+            return direct;
+        }
+
+        let margin = 2.0 * self.get_lon_step().max(self.get_lat_step());
+        let bounding_box = (
+            lon.min(node_position[0]) - margin,
+            lat.min(node_position[1]) - margin,
+            lon.max(node_position[0]) + margin,
+            lat.max(node_position[1]) + margin,
+        );
+        // Positions and nodes on different sides of the antimeridian aren't connected around the coast
+        if bounding_box.0 < -180.0
+            || bounding_box.2 > 180.0
+            || bounding_box.2 - bounding_box.0 > 180.0
+        {
+            return direct;
+        }
+        let is_inside = |p: [f64; 2]| {
+            p[0] >= bounding_box.0
+                && p[0] <= bounding_box.2
+                && p[1] >= bounding_box.1
+                && p[1] <= bounding_box.3
+        };
+
+        let segments: Vec<usize> = self
+            .coastlines
+            .get_segments_in_bounding_box(bounding_box)
+            .into_iter()
+            .filter(|s| {
+                let segment = &self.coastlines.segments[*s];
+                segment.start[0].max(segment.end[0]) >= bounding_box.0
+                    && segment.start[1].max(segment.end[1]) >= bounding_box.1
+                    && segment.start[0].min(segment.end[0]) <= bounding_box.2
+                    && segment.start[1].min(segment.end[1]) <= bounding_box.3
+            })
+            .collect();
+        if segments.len() > MAX_VISIBILITY_SEGMENTS {
+            println!(
+                "Too many coastline segments ({}) around {},{}, using a straight line",
+                segments.len(),
+                lon,
+                lat
+            );
+            return direct;
+        }
+        let mut vertices = vec![[lon, lat]];
+        for segment in segments.iter().map(|s| &self.coastlines.segments[*s]) {
+            if !is_inside(segment.start) {
+                continue;
+            }
+
+            // Move the vertex along the bisector of its edges away from the land
+            let left_normal = |from: [f64; 2], to: [f64; 2]| {
+                let (dx, dy) = (to[0] - from[0], to[1] - from[1]);
+                let length = dx.hypot(dy);
+                if length == 0.0 {
+                    [0.0, 0.0]
+                } else {
+                    [-dy / length, dx / length]
+                }
+            };
+            let first = left_normal(segment.previous, segment.start);
+            let second = left_normal(segment.start, segment.end);
+            let sign = if segment.is_land_left { -1.0 } else { 1.0 };
+            let direction = [sign * (first[0] + second[0]), sign * (first[1] + second[1])];
+            let length = direction[0].hypot(direction[1]);
+            if length > 0.0 {
+                vertices.push([
+                    segment.start[0] + direction[0] / length * VERTEX_OFFSET,
+                    segment.start[1] + direction[1] / length * VERTEX_OFFSET,
+                ]);
+            }
+        }
+        if vertices.len() > MAX_VISIBILITY_VERTICES {
+            println!(
+                "Too many coastline vertices ({}) around {},{}, using a straight line",
+                vertices.len(),
+                lon,
+                lat
+            );
+            return direct;
+        }
+
+        // Any water node around the position which is connected to the snapped node can be the target
+        let coastline_vertices_count = vertices.len();
+        let mut targets = Vec::new();
+        for target in self.get_nodes_in_bounding_box(bounding_box) {
+            let position = [self.get_lon(target), self.get_lat(target)];
+            if is_inside(position)
+                && self.offsets[target] != self.offsets[target + 1]
+                && !restrictions.is_node_blocked(target)
+                && self.are_connected(target, node)
+            {
+                vertices.push(position);
+                targets.push(target);
+            }
+        }
+
+        let is_visible = |a: [f64; 2], b: [f64; 2]| {
+            !segments
+                .iter()
+                .map(|s| &self.coastlines.segments[*s])
+                .any(|segment| segments_intersect(a, b, segment.start, segment.end))
+        };
+
+        // Dijkstra on the visibility graph, edges are only checked if they would improve a distance
+        let mut distances = vec![u32::MAX; vertices.len()];
+        let mut predecessors = vec![usize::MAX; vertices.len()];
+        let mut is_settled = vec![false; vertices.len()];
+        distances[0] = 0;
+        loop {
+            let current = (0..vertices.len())
+                .filter(|v| !is_settled[*v] && distances[*v] != u32::MAX)
+                .min_by_key(|v| distances[*v]);
+            let current = match current {
+                Some(current) => current,
+                None => break,
+            };
+            is_settled[current] = true;
+
+            if current >= coastline_vertices_count {
+                let mut positions = vec![vertices[current]];
+                let mut v = current;
+                while predecessors[v] != usize::MAX {
+                    v = predecessors[v];
+                    positions.push(vertices[v]);
+                }
+                positions.reverse();
+                return (targets[current - coastline_vertices_count], positions);
+            }
+
+            let [lon1, lat1] = vertices[current];
+            for next in 0..vertices.len() {
+                if is_settled[next] {
+                    continue;
+                }
+                let [lon2, lat2] = vertices[next];
+                let distance =
+                    distances[current] + Self::calculate_distance(lon1, lat1, lon2, lat2);
+                if distance < distances[next] && is_visible(vertices[current], vertices[next]) {
+                    distances[next] = distance;
+                    predecessors[next] = current;
+                }
+            }
+        }
+
+        println!(
+            "No land safe connection from {},{} to the grid, using a straight line",
+            lon, lat
+        );
+        direct
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::VecDeque;
+
+use crate::Graph;
+
+pub const NO_COMPONENT: u32 = u32::MAX;
+
+impl Graph {
+    // Assigns every water node the index of its connected component, land nodes get NO_COMPONENT. Edges are
+    // followed in both directions, as the cost model removes edges against strong drift and the graph isn't
+    // symmetric then, so a component may contain nodes which can't reach each other.
+    pub fn label_components(&mut self) {
+        let node_count = self.offsets.len() - 1;
+        let mut components = vec![NO_COMPONENT; node_count];
+        let mut component_sizes = Vec::new();
+        let mut queue = VecDeque::new();
+
+        // Sources of the incoming edges of each node
+        let mut reverse_offsets = vec![0u32; node_count + 1];
+        for edge in self.edges.iter() {
+            reverse_offsets[edge.destination as usize + 1] += 1;
+        }
+        for node in 0..node_count {
+            reverse_offsets[node + 1] += reverse_offsets[node];
+        }
+        let mut sources = vec![0u32; self.edges.len()];
+        let mut next_source = reverse_offsets.clone();
+        for node in 0..node_count {
+            for e in self.offsets[node].0..self.offsets[node + 1].0 {
+                let dest = self.edges[e as usize].destination as usize;
+                sources[next_source[dest] as usize] = node as u32;
+                next_source[dest] += 1;
+            }
+        }
+
+        for start in 0..node_count {
+            if components[start] != NO_COMPONENT
+                || (self.offsets[start] == self.offsets[start + 1]
+                    && reverse_offsets[start] == reverse_offsets[start + 1])
+            {
+                continue;
+            }
+            let component = component_sizes.len() as u32;
+            let mut size = 0;
+            components[start] = component;
+            queue.push_back(start);
+            while let Some(node) = queue.pop_front() {
+                size += 1;
+                let destinations = (self.offsets[node].0..self.offsets[node + 1].0)
+                    .map(|e| self.edges[e as usize].destination);
+                let reverse_sources = sources
+                    [reverse_offsets[node] as usize..reverse_offsets[node + 1] as usize]
+                    .iter()
+                    .copied();
+                for neighbor in destinations.chain(reverse_sources) {
+                    if components[neighbor as usize] == NO_COMPONENT {
+                        components[neighbor as usize] = component;
+                        queue.push_back(neighbor as usize);
+                    }
+                }
+            }
+            component_sizes.push(size);
+        }
+
+        self.largest_component = component_sizes
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, size)| **size)
+            .map_or(NO_COMPONENT, |(i, _)| i as u32);
+        self.components = components;
+    }
+
+    // Nodes of different components can't reach each other, so searches between them are skipped. Nodes of the
+    // same component may still be unreachable if the graph isn't symmetric.
+    pub fn are_connected(&self, first: usize, second: usize) -> bool {
+        self.components.is_empty() || self.components[first] == self.components[second]
+    }
+
+    // True for all water nodes if the components haven't been labeled
+    pub fn is_in_largest_component(&self, node: usize) -> bool {
+        self.components.is_empty() || self.components[node] == self.largest_component
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Edge;
+
+    // Graph with the outgoing edges of each node, distances don't matter for the labels
+    fn create_graph(adjacency: &[&[u32]]) -> Graph {
+        let mut graph = Graph::default();
+        for destinations in adjacency {
+            graph.offsets.push((graph.edges.len() as u32, None));
+            graph.edges.extend(destinations.iter().map(|d| Edge {
+                destination: *d,
+                distance: 1,
+            }));
+        }
+        graph.offsets.push((graph.edges.len() as u32, None));
+        graph
+    }
+
+    #[test]
+    fn label_components_of_directed_graph() {
+        // 2 only leaves towards 0, 3 is only entered from 1, 4 is land and 5 and 6 form a separate basin
+        let mut graph = create_graph(&[&[1], &[0, 3], &[0], &[], &[], &[6], &[5]]);
+        graph.label_components();
+
+        let c = &graph.components;
+        assert!(c[0] == c[1] && c[1] == c[2] && c[2] == c[3]);
+        assert_eq!(c[4], NO_COMPONENT);
+        assert_eq!(c[5], c[6]);
+        assert_ne!(c[0], c[5]);
+        assert_eq!(graph.largest_component, c[0]);
+        assert!(graph.are_connected(3, 0));
+        assert!(!graph.are_connected(0, 6));
+    }
+}
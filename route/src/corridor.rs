@@ -0,0 +1,113 @@
+use crate::zones::sample_segment;
+use crate::{
+    AlgorithmState, ExecutionType, Graph, QueryOptions, Restrictions, Route,
+    METERS_PER_NAUTICAL_MILE,
+};
+
+// Distance in meters from the coarse route to the border of the corridor if the query doesn't set one
+pub const DEFAULT_CORRIDOR_WIDTH: f64 = 50.0 * METERS_PER_NAUTICAL_MILE;
+// Number of times the corridor is doubled before the whole graph is searched
+const CORRIDOR_WIDENINGS: usize = 2;
+
+impl Graph {
+    // Restricts the search to the nodes which are at most about the width (in meters) away from the route
+    pub fn restrict_to_corridor(
+        &self,
+        route: &[[f64; 2]],
+        width: f64,
+        restrictions: &mut Restrictions,
+    ) {
+        let width_degrees = (width / 6371000.0).to_degrees();
+        // The samples are one nautical mile apart, boxes around every few of them overlap
+        let sample_step = ((width / 2.0 / METERS_PER_NAUTICAL_MILE) as usize).max(1);
+        let mut positions = route.to_vec();
+        for segment in route.windows(2) {
+            let (_, samples) = sample_segment(segment[0], segment[1]);
+            positions.extend(samples.into_iter().step_by(sample_step));
+        }
+
+        let mut is_inside = vec![false; self.node_count()];
+        for [lon, lat] in positions {
+            let max_lat = lat.abs() + width_degrees;
+            let lon_width = if max_lat >= 90.0 {
+                180.0
+            } else {
+                (width_degrees / max_lat.to_radians().cos()).min(180.0)
+            };
+            for node in self.get_nodes_in_bounding_box((
+                lon - lon_width,
+                lat - width_degrees,
+                lon + lon_width,
+                lat + width_degrees,
+            )) {
+                is_inside[node] = true;
+            }
+        }
+        restrictions.corridor_nodes = is_inside;
+    }
+
+    // Searches this graph only in a corridor around the route on the coarse graph. The corridor is widened if
+    // there is no route inside of it and at last the whole graph is searched.
+    #[allow(clippy::too_many_arguments)]
+    pub fn find_path_in_corridor(
+        &self,
+        coarse_graph: &Graph,
+        lon1: f64,
+        lat1: f64,
+        lon2: f64,
+        lat2: f64,
+        execution_type: &ExecutionType,
+        options: &QueryOptions,
+        state: &mut AlgorithmState,
+    ) -> Result<Route, String> {
+        // Snapped nodes of the query belong to this graph
+        let coarse_options = QueryOptions {
+            start_node: None,
+            end_node: None,
+            ..options.clone()
+        };
+        let mut coarse_state = AlgorithmState::new(coarse_graph.node_count());
+        let coarse_route = match coarse_graph.find_path(
+            lon1,
+            lat1,
+            lon2,
+            lat2,
+            execution_type,
+            &coarse_options,
+            &mut coarse_state,
+        ) {
+            Ok(route) => route,
+            Err(e) => {
+                println!(
+                    "No route on the coarse graph ({}), searching the whole graph",
+                    e
+                );
+                return self.find_path(lon1, lat1, lon2, lat2, execution_type, options, state);
+            }
+        };
+
+        let mut corridor_options = QueryOptions {
+            corridor: coarse_route.waypoints,
+            ..options.clone()
+        };
+        let mut width = options.corridor_width.unwrap_or(DEFAULT_CORRIDOR_WIDTH);
+        for _ in 0..=CORRIDOR_WIDENINGS {
+            corridor_options.corridor_width = Some(width);
+            match self.find_path(
+                lon1,
+                lat1,
+                lon2,
+                lat2,
+                execution_type,
+                &corridor_options,
+                state,
+            ) {
+                Ok(route) => return Ok(route),
+                Err(e) => println!("No route in the corridor of {} m ({})", width, e),
+            }
+            width *= 2.0;
+        }
+        println!("Searching the whole graph");
+        self.find_path(lon1, lat1, lon2, lat2, execution_type, options, state)
+    }
+}
@@ -0,0 +1,179 @@
+use std::fs;
+
+use serde_json::{Map, Value};
+
+use crate::zones::{self, Zone};
+use crate::METERS_PER_NAUTICAL_MILE;
+
+// Tonnes of CO2 per tonne of fuel (IMO carbon factors)
+const HFO_CO2_FACTOR: f64 = 3.114;
+const MGO_CO2_FACTOR: f64 = 3.206;
+
+// Fuel consumption in tonnes per day depending on the speed through water in knots
+pub struct FuelCurve {
+    pub points: Vec<(f64, f64)>, // Sorted by speed
+}
+
+impl FuelCurve {
+    // Expects one "speed,fuel" line per point of the curve, lines starting with '#' or a letter are skipped.
+    // The curve needs at least one point.
+    pub fn new_from_csvfile(filename: &str) -> Self {
+        println!("Creating fuel curve from csv file: {}", filename);
+        let content = fs::read_to_string(filename)
+            .unwrap_or_else(|e| panic!("Can't read fuel curve {}: {}", filename, e));
+
+        let mut points = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(char::is_alphabetic) {
+                continue;
+            }
+            let point = line.split_once(',').and_then(|(speed, fuel)| {
+                Some((speed.trim().parse().ok()?, fuel.trim().parse().ok()?))
+            });
+            match point {
+                Some(point) => points.push(point),
+                None => panic!(
+                    "Invalid point \"{}\" in line {} of fuel curve {}, expected speed,fuel",
+                    line,
+                    i + 1,
+                    filename
+                ),
+            }
+        }
+        if points.is_empty() {
+            panic!("Fuel curve {} doesn't contain any points", filename);
+        }
+        points.sort_by(|a: &(f64, f64), b| a.0.partial_cmp(&b.0).unwrap());
+        println!("Created fuel curve with {} points", points.len());
+        FuelCurve { points }
+    }
+
+    // Linear interpolation between the points, constant outside of the curve
+    pub fn get_fuel_per_day(&self, speed: f64) -> f64 {
+        let next = self.points.partition_point(|p| p.0 < speed);
+        if next == 0 {
+            return self.points[0].1;
+        }
+        if next == self.points.len() {
+            return self.points[next - 1].1;
+        }
+        let (speed1, fuel1) = self.points[next - 1];
+        let (speed2, fuel2) = self.points[next];
+        fuel1 + (fuel2 - fuel1) * (speed - speed1) / (speed2 - speed1)
+    }
+}
+
+// Vessels burn heavy fuel oil at sea and switch to marine gas oil inside of Emission Control Areas
+pub struct FuelModel {
+    pub curve: FuelCurve,
+    pub vessel_speed: f64, // Knots
+    pub eca: Zone,         // All ECA polygons
+    pub hfo_price: f64,    // Per tonne
+    pub mgo_price: f64,    // Per tonne
+}
+
+#[derive(serde::Serialize)]
+pub struct FuelConsumption {
+    pub fuel_type: &'static str,
+    pub distance: f64, // Nautical miles
+    pub fuel: f64,     // Tonnes
+    pub co2: f64,      // Tonnes
+    pub cost: f64,
+}
+
+#[derive(serde::Serialize)]
+pub struct Emissions {
+    pub eca_distance: f64,     // Nautical miles
+    pub non_eca_distance: f64, // Nautical miles
+    pub fuel: f64,             // Tonnes
+    pub co2: f64,              // Tonnes
+    pub cost: f64,
+    pub fuel_types: Vec<FuelConsumption>,
+}
+
+impl FuelModel {
+    pub fn new(
+        curve: FuelCurve,
+        vessel_speed: f64,
+        ecas: Vec<Zone>,
+        hfo_price: f64,
+        mgo_price: f64,
+    ) -> Self {
+        let eca = Zone {
+            name: Some("ECA".to_string()),
+            polygons: ecas.into_iter().flat_map(|z| z.polygons).collect(),
+            properties: Map::new(),
+        };
+        FuelModel {
+            curve,
+            vessel_speed,
+            eca,
+            hfo_price,
+            mgo_price,
+        }
+    }
+
+    // Penalty zone which makes distances inside of ECAs as expensive as the fuel burnt there
+    pub fn get_eca_penalty_zone(&self) -> Zone {
+        let mut properties = Map::new();
+        properties.insert(
+            "cost_factor".to_string(),
+            Value::from(self.mgo_price / self.hfo_price),
+        );
+        Zone {
+            properties,
+            ..self.eca.clone()
+        }
+    }
+
+    // Splits the route into the parts inside and outside of ECAs, the travel time in seconds is derived
+    // from the vessel speed if it isn't known
+    pub fn estimate(
+        &self,
+        coordinates: &[[f64; 2]],
+        distance: f64,
+        travel_time: Option<f64>,
+    ) -> Emissions {
+        let eca_distance =
+            zones::measure_zone_distances(coordinates, std::slice::from_ref(&self.eca))[0]
+                .min(distance);
+        let travel_time =
+            travel_time.unwrap_or(distance / METERS_PER_NAUTICAL_MILE / self.vessel_speed * 3600.0);
+        let fuel_per_day = self.curve.get_fuel_per_day(self.vessel_speed);
+
+        let mut fuel_types = Vec::new();
+        for (fuel_type, part_distance, co2_factor, price) in [
+            (
+                "HFO",
+                distance - eca_distance,
+                HFO_CO2_FACTOR,
+                self.hfo_price,
+            ),
+            ("MGO", eca_distance, MGO_CO2_FACTOR, self.mgo_price),
+        ] {
+            let share = if distance > 0.0 {
+                part_distance / distance
+            } else {
+                0.0
+            };
+            let fuel = fuel_per_day * travel_time * share / 86400.0;
+            fuel_types.push(FuelConsumption {
+                fuel_type,
+                distance: part_distance / METERS_PER_NAUTICAL_MILE,
+                fuel,
+                co2: fuel * co2_factor,
+                cost: fuel * price,
+            });
+        }
+
+        Emissions {
+            eca_distance: eca_distance / METERS_PER_NAUTICAL_MILE,
+            non_eca_distance: (distance - eca_distance) / METERS_PER_NAUTICAL_MILE,
+            fuel: fuel_types.iter().map(|f| f.fuel).sum(),
+            co2: fuel_types.iter().map(|f| f.co2).sum(),
+            cost: fuel_types.iter().map(|f| f.cost).sum(),
+            fuel_types,
+        }
+    }
+}
@@ -0,0 +1,733 @@
+use std::{collections::BTreeMap, fs};
+
+use crate::{AStarHeapNode, AlgorithmState, Graph, PathResult, METERS_PER_NAUTICAL_MILE};
+
+const KNOTS_TO_METERS_PER_SECOND: f64 = METERS_PER_NAUTICAL_MILE / 3600.0;
+
+// Ships can't make any meaningful headway below this speed over ground (m/s)
+const MIN_SPEED_OVER_GROUND: f64 = 0.1;
+
+// Regular lon/lat grid of u (eastward) and v (northward) components in m/s, rows start at min_lat
+pub struct VectorField {
+    pub min_lon: f64,
+    pub min_lat: f64,
+    pub lon_step: f64,
+    pub lat_step: f64,
+    pub columns_count: usize,
+    pub rows_count: usize,
+    pub u: Vec<f32>,
+    pub v: Vec<f32>,
+}
+
+impl VectorField {
+    // Expects one "lon,lat,u,v" line per grid cell, lines starting with '#' or a letter are skipped
+    pub fn new_from_csvfile(filename: &str) -> Self {
+        println!("Creating vector field from csv file: {}", filename);
+        let content = fs::read_to_string(filename)
+            .unwrap_or_else(|e| panic!("Can't read vector field {}: {}", filename, e));
+
+        let mut values = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(char::is_alphabetic) {
+                continue;
+            }
+            let columns = parse_numbers(line, 4).unwrap_or_else(|| {
+                panic!(
+                    "Invalid line {} of vector field {}, expected lon,lat,u,v: \"{}\"",
+                    i + 1,
+                    filename,
+                    line
+                )
+            });
+            values.push((columns[0], columns[1], columns[2], columns[3]));
+        }
+
+        let field = Self::new_from_values(&values);
+        println!(
+            "Created vector field with {}x{} cells",
+            field.columns_count, field.rows_count
+        );
+        field
+    }
+
+    pub fn new_from_values(values: &[(f64, f64, f64, f64)]) -> Self {
+        let mut lons: Vec<f64> = values.iter().map(|v| v.0).collect();
+        let mut lats: Vec<f64> = values.iter().map(|v| v.1).collect();
+        for coordinates in [&mut lons, &mut lats] {
+            coordinates.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            coordinates.dedup_by(|a, b| (*a - *b).abs() < 1e-6);
+        }
+
+        let columns_count = lons.len();
+        let rows_count = lats.len();
+        let lon_step = if columns_count > 1 {
+            (lons[columns_count - 1] - lons[0]) / (columns_count - 1) as f64
+        } else {
+            1.0
+        };
+        let lat_step = if rows_count > 1 {
+            (lats[rows_count - 1] - lats[0]) / (rows_count - 1) as f64
+        } else {
+            1.0
+        };
+
+        let mut field = VectorField {
+            min_lon: lons[0],
+            min_lat: lats[0],
+            lon_step,
+            lat_step,
+            columns_count,
+            rows_count,
+            u: vec![0.0; columns_count * rows_count],
+            v: vec![0.0; columns_count * rows_count],
+        };
+
+        for (lon, lat, u, v) in values.iter() {
+            let column = ((lon - field.min_lon) / lon_step).round() as usize;
+            let row = ((lat - field.min_lat) / lat_step).round() as usize;
+            // Missing values (e.g. land cells) are stored as NaN and count as calm
+            if u.is_finite() && v.is_finite() {
+                field.u[row * columns_count + column] = *u as f32;
+                field.v[row * columns_count + column] = *v as f32;
+            }
+        }
+        field
+    }
+
+    fn wraps_around(&self) -> bool {
+        self.lon_step * self.columns_count as f64 >= 360.0 - 1e-6
+    }
+
+    // Bilinear interpolation of the vector at the given position, zero outside of the grid
+    pub fn sample(&self, lon: f64, lat: f64) -> (f64, f64) {
+        let x = (lon - self.min_lon) / self.lon_step;
+        let y = (lat - self.min_lat) / self.lat_step;
+        if y < 0.0 || y > (self.rows_count - 1) as f64 {
+            return (0.0, 0.0);
+        }
+        let x = if self.wraps_around() {
+            x.rem_euclid(self.columns_count as f64)
+        } else if x < 0.0 || x > (self.columns_count - 1) as f64 {
+            return (0.0, 0.0);
+        } else {
+            x
+        };
+
+        let column_left = x.floor() as usize;
+        let column_right = if self.wraps_around() {
+            (column_left + 1) % self.columns_count
+        } else {
+            (column_left + 1).min(self.columns_count - 1)
+        };
+        let row_bottom = y.floor() as usize;
+        let row_top = (row_bottom + 1).min(self.rows_count - 1);
+        let fx = x - x.floor();
+        let fy = y - y.floor();
+
+        let interpolate = |values: &[f32]| {
+            let bottom = values[row_bottom * self.columns_count + column_left] as f64 * (1.0 - fx)
+                + values[row_bottom * self.columns_count + column_right] as f64 * fx;
+            let top = values[row_top * self.columns_count + column_left] as f64 * (1.0 - fx)
+                + values[row_top * self.columns_count + column_right] as f64 * fx;
+            bottom * (1.0 - fy) + top * fy
+        };
+        (interpolate(&self.u), interpolate(&self.v))
+    }
+
+    pub fn max_magnitude(&self) -> f64 {
+        self.u
+            .iter()
+            .zip(self.v.iter())
+            .map(|(u, v)| (*u as f64).hypot(*v as f64))
+            .fold(0.0, f64::max)
+    }
+}
+
+// Turns currents and wind into direction dependent travel times for a vessel with constant speed through water
+pub struct CostModel {
+    pub vessel_speed: f64,
+    pub currents: Option<VectorField>,
+    pub wind: Option<VectorField>,
+    // Fraction of the wind speed that pushes the vessel (leeway)
+    pub wind_factor: f64,
+    // Upper bound for the speed over ground, used to express travel times as distances
+    pub reference_speed: f64,
+}
+
+impl CostModel {
+    pub fn new(
+        vessel_speed_knots: f64,
+        currents: Option<VectorField>,
+        wind: Option<VectorField>,
+        wind_factor: f64,
+    ) -> Self {
+        let vessel_speed = vessel_speed_knots * KNOTS_TO_METERS_PER_SECOND;
+        let max_drift = currents.as_ref().map_or(0.0, |c| c.max_magnitude())
+            + wind_factor * wind.as_ref().map_or(0.0, |w| w.max_magnitude());
+        CostModel {
+            vessel_speed,
+            currents,
+            wind,
+            wind_factor,
+            reference_speed: vessel_speed + max_drift,
+        }
+    }
+
+    pub fn drift(&self, lon: f64, lat: f64) -> (f64, f64) {
+        let mut drift = (0.0, 0.0);
+        if let Some(currents) = &self.currents {
+            let (u, v) = currents.sample(lon, lat);
+            drift.0 += u;
+            drift.1 += v;
+        }
+        if let Some(wind) = &self.wind {
+            let (u, v) = wind.sample(lon, lat);
+            drift.0 += self.wind_factor * u;
+            drift.1 += self.wind_factor * v;
+        }
+        drift
+    }
+
+    pub fn speed_over_ground(&self, lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> Option<f64> {
+        calculate_speed_over_ground(self.vessel_speed, lon1, lat1, lon2, lat2, |lon, lat| {
+            self.drift(lon, lat)
+        })
+    }
+
+    // Travel time in seconds, None if the vessel can't make headway against the drift
+    pub fn travel_time(&self, lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> Option<f64> {
+        let speed = self.speed_over_ground(lon1, lat1, lon2, lat2)?;
+        Some(Graph::calculate_distance(lon1, lat1, lon2, lat2) as f64 / speed)
+    }
+
+    // Travel time expressed as the distance covered at the reference speed. This keeps edge weights in
+    // meters and never below the actual distance, so the A* heuristics stay admissible.
+    pub fn edge_weight(&self, lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> Option<u32> {
+        let travel_time = self.travel_time(lon1, lat1, lon2, lat2)?;
+        Some((travel_time * self.reference_speed) as u32)
+    }
+}
+
+// Speed over ground when steering a straight course from the first to the second position.
+// The vessel crabs against the cross drift and the along track drift adds to its speed.
+fn calculate_speed_over_ground(
+    vessel_speed: f64,
+    lon1: f64,
+    lat1: f64,
+    lon2: f64,
+    lat2: f64,
+    drift: impl Fn(f64, f64) -> (f64, f64),
+) -> Option<f64> {
+    let mut delta_lon = lon2 - lon1;
+    if delta_lon > 180.0 {
+        delta_lon -= 360.0;
+    } else if delta_lon < -180.0 {
+        delta_lon += 360.0;
+    }
+    let mid_lon = lon1 + delta_lon / 2.0;
+    let mid_lat = (lat1 + lat2) / 2.0;
+
+    let east = delta_lon * mid_lat.to_radians().cos();
+    let north = lat2 - lat1;
+    let length = east.hypot(north);
+    if length == 0.0 {
+        return Some(vessel_speed);
+    }
+    let (east, north) = (east / length, north / length);
+
+    let (drift_east, drift_north) = drift(mid_lon, mid_lat);
+    let drift_along = drift_east * east + drift_north * north;
+    let drift_cross = -drift_east * north + drift_north * east;
+    if drift_cross.abs() >= vessel_speed {
+        return None;
+    }
+
+    let speed = (vessel_speed.powi(2) - drift_cross.powi(2)).sqrt() + drift_along;
+    if speed < MIN_SPEED_OVER_GROUND {
+        return None;
+    }
+    Some(speed)
+}
+
+impl Graph {
+    // Replaces the edge distances by direction dependent travel times, edges the vessel can't sail are removed
+    pub fn apply_cost_model(&mut self, cost_model: CostModel) {
+        assert!(
+            self.shortcut_rectangles.is_empty(),
+            "Cost models can only be applied to graphs without shortcut rectangles"
+        );
+        assert!(
+            self.canals.is_empty(),
+            "Cost models have to be applied before adding canals"
+        );
+        println!("Applying cost model to {} edges", self.edges.len());
+
+        let mut offsets = Vec::with_capacity(self.offsets.len());
+        let mut edges = Vec::with_capacity(self.edges.len());
+        let mut removed_edges = 0;
+        for i in 0..self.offsets.len() - 1 {
+            offsets.push((edges.len() as u32, self.offsets[i].1));
+            for e in self.offsets[i].0 as usize..self.offsets[i + 1].0 as usize {
+                let mut edge = self.edges[e];
+                let dest = edge.destination as usize;
+                match cost_model.edge_weight(
+                    self.get_lon(i),
+                    self.get_lat(i),
+                    self.get_lon(dest),
+                    self.get_lat(dest),
+                ) {
+                    Some(weight) => {
+                        edge.distance = weight;
+                        edges.push(edge);
+                    }
+                    None => removed_edges += 1,
+                }
+            }
+        }
+        offsets.push((edges.len() as u32, None));
+        println!("Removed {} edges against strong drift", removed_edges);
+
+        self.offsets = offsets;
+        self.edges = edges;
+        self.cost_model = Some(cost_model);
+    }
+
+    // Backward searches need the weight of the edge pointing to the given node, which differs when a
+    // cost model is applied
+    pub fn get_reverse_distance(&self, edge_index: usize, node: usize) -> Option<u32> {
+        if self.cost_model.is_none() {
+            return Some(self.edges[edge_index].distance);
+        }
+        let dest = self.edges[edge_index].destination as usize;
+        (self.offsets[dest].0 as usize..self.offsets[dest + 1].0 as usize)
+            .map(|e| self.edges[e])
+            .find(|e| e.destination as usize == node)
+            .map(|e| e.distance)
+    }
+}
+
+// Vector fields for consecutive points in time, e.g. from a weather forecast
+pub struct VectorFieldSeries {
+    pub times: Vec<i64>,
+    pub fields: Vec<VectorField>,
+}
+
+impl VectorFieldSeries {
+    // Expects one "time,lon,lat,u,v" line per grid cell and time step, see parse_timestamp for the time format
+    pub fn new_from_csvfile(filename: &str) -> Self {
+        println!("Creating vector field series from csv file: {}", filename);
+        let content = fs::read_to_string(filename)
+            .unwrap_or_else(|e| panic!("Can't read vector field series {}: {}", filename, e));
+
+        let mut values = BTreeMap::<i64, Vec<(f64, f64, f64, f64)>>::new();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(char::is_alphabetic) {
+                continue;
+            }
+            let (time, columns) = line
+                .split_once(',')
+                .and_then(|(time, rest)| Some((parse_timestamp(time.trim())?, parse_numbers(rest, 4)?)))
+                .unwrap_or_else(|| {
+                    panic!(
+                        "Invalid line {} of vector field series {}, expected time,lon,lat,u,v: \"{}\"",
+                        i + 1,
+                        filename,
+                        line
+                    )
+                });
+            values
+                .entry(time)
+                .or_default()
+                .push((columns[0], columns[1], columns[2], columns[3]));
+        }
+
+        let series = VectorFieldSeries {
+            times: values.keys().copied().collect(),
+            fields: values
+                .values()
+                .map(|v| VectorField::new_from_values(v))
+                .collect(),
+        };
+        println!(
+            "Created vector field series with {} time steps",
+            series.times.len()
+        );
+        series
+    }
+
+    // Linear interpolation between the two surrounding time steps, the first/last field is used outside of the series
+    pub fn sample(&self, lon: f64, lat: f64, time: i64) -> (f64, f64) {
+        let next = self.times.partition_point(|t| *t <= time);
+        if next == 0 {
+            return self.fields[0].sample(lon, lat);
+        }
+        if next == self.times.len() {
+            return self.fields[next - 1].sample(lon, lat);
+        }
+
+        let factor =
+            (time - self.times[next - 1]) as f64 / (self.times[next] - self.times[next - 1]) as f64;
+        let (u1, v1) = self.fields[next - 1].sample(lon, lat);
+        let (u2, v2) = self.fields[next].sample(lon, lat);
+        (u1 + (u2 - u1) * factor, v1 + (v2 - v1) * factor)
+    }
+
+    pub fn max_magnitude(&self) -> f64 {
+        self.fields
+            .iter()
+            .map(|f| f.max_magnitude())
+            .fold(0.0, f64::max)
+    }
+}
+
+// Time dependent variant of the cost model based on weather forecasts
+pub struct WeatherModel {
+    pub vessel_speed: f64,
+    pub currents: Option<VectorFieldSeries>,
+    pub wind: Option<VectorFieldSeries>,
+    pub wind_factor: f64,
+    pub reference_speed: f64,
+    // Time steps of all series, travel times are interpolated linearly in between
+    pub times: Vec<i64>,
+}
+
+impl WeatherModel {
+    pub fn new(
+        vessel_speed_knots: f64,
+        currents: Option<VectorFieldSeries>,
+        wind: Option<VectorFieldSeries>,
+        wind_factor: f64,
+    ) -> Self {
+        let vessel_speed = vessel_speed_knots * KNOTS_TO_METERS_PER_SECOND;
+        let max_drift = currents.as_ref().map_or(0.0, |c| c.max_magnitude())
+            + wind_factor * wind.as_ref().map_or(0.0, |w| w.max_magnitude());
+
+        let mut times = Vec::new();
+        for series in [&currents, &wind].into_iter().flatten() {
+            times.extend_from_slice(&series.times);
+        }
+        times.sort_unstable();
+        times.dedup();
+
+        WeatherModel {
+            vessel_speed,
+            currents,
+            wind,
+            wind_factor,
+            reference_speed: vessel_speed + max_drift,
+            times,
+        }
+    }
+
+    pub fn drift(&self, lon: f64, lat: f64, time: i64) -> (f64, f64) {
+        let mut drift = (0.0, 0.0);
+        if let Some(currents) = &self.currents {
+            let (u, v) = currents.sample(lon, lat, time);
+            drift.0 += u;
+            drift.1 += v;
+        }
+        if let Some(wind) = &self.wind {
+            let (u, v) = wind.sample(lon, lat, time);
+            drift.0 += self.wind_factor * u;
+            drift.1 += self.wind_factor * v;
+        }
+        drift
+    }
+
+    fn calculate_travel_time(
+        &self,
+        lon1: f64,
+        lat1: f64,
+        lon2: f64,
+        lat2: f64,
+        time: i64,
+    ) -> Option<f64> {
+        let speed =
+            calculate_speed_over_ground(self.vessel_speed, lon1, lat1, lon2, lat2, |lon, lat| {
+                self.drift(lon, lat, time)
+            })?;
+        Some(Graph::calculate_distance(lon1, lat1, lon2, lat2) as f64 / speed)
+    }
+
+    // Travel time in seconds when departing at the given time. The travel time function is linear between
+    // two time steps and its slope is limited, so departing later never results in an earlier arrival (FIFO).
+    pub fn travel_time(
+        &self,
+        lon1: f64,
+        lat1: f64,
+        lon2: f64,
+        lat2: f64,
+        departure: i64,
+    ) -> Option<f64> {
+        let next = self.times.partition_point(|t| *t <= departure);
+        if next == 0 || next == self.times.len() {
+            return self.calculate_travel_time(lon1, lat1, lon2, lat2, departure);
+        }
+
+        let (time1, time2) = (self.times[next - 1], self.times[next]);
+        let travel_time1 = self.calculate_travel_time(lon1, lat1, lon2, lat2, time1)?;
+        let travel_time2 = self
+            .calculate_travel_time(lon1, lat1, lon2, lat2, time2)?
+            .max(travel_time1 - (time2 - time1) as f64);
+        let factor = (departure - time1) as f64 / (time2 - time1) as f64;
+        Some(travel_time1 + (travel_time2 - travel_time1) * factor)
+    }
+}
+
+// Comma separated numbers, None unless there are exactly count of them
+fn parse_numbers(line: &str, count: usize) -> Option<Vec<f64>> {
+    let numbers: Vec<f64> = line
+        .split(',')
+        .map(|c| c.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    (numbers.len() == count).then_some(numbers)
+}
+
+// Parses unix timestamps as well as dates like "2021-12-24", "2021-12-24T18:00" or "2021-12-24T18:00:00Z" (UTC)
+pub fn parse_timestamp(s: &str) -> Option<i64> {
+    if let Ok(timestamp) = s.parse::<i64>() {
+        return Some(timestamp);
+    }
+
+    let s = s.trim_end_matches('Z');
+    let (date, time) = s.split_once(['T', ' ']).unwrap_or((s, "00:00"));
+    let date: Vec<i64> = date
+        .split('-')
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    let time: Vec<i64> = time
+        .split(':')
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    if date.len() != 3 || time.len() < 2 || time.len() > 3 {
+        return None;
+    }
+    let (year, month, day) = (date[0], date[1], date[2]);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Days since 1970-01-01 in the proleptic gregorian calendar
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    Some(days * 86400 + time[0] * 3600 + time[1] * 60 + time.get(2).unwrap_or(&0))
+}
+
+impl Graph {
+    pub fn set_weather_model(&mut self, weather_model: WeatherModel) {
+        assert!(
+            self.shortcut_rectangles.is_empty(),
+            "Weather models can only be used with graphs without shortcut rectangles"
+        );
+        self.weather_model = Some(weather_model);
+    }
+
+    // Earliest arrival search, the distances of the state hold the seconds passed since the departure
+    pub fn time_dependent_dijkstra(
+        &self,
+        start: usize,
+        end: usize,
+        departure: i64,
+        state: &mut AlgorithmState,
+    ) -> PathResult {
+        self.time_dependent_search(start, end, departure, false, state)
+    }
+
+    pub fn time_dependent_a_star(
+        &self,
+        start: usize,
+        end: usize,
+        departure: i64,
+        state: &mut AlgorithmState,
+    ) -> PathResult {
+        self.time_dependent_search(start, end, departure, true, state)
+    }
+
+    fn time_dependent_search(
+        &self,
+        start: usize,
+        end: usize,
+        departure: i64,
+        use_heuristic: bool,
+        state: &mut AlgorithmState,
+    ) -> PathResult {
+        let weather_model = self.weather_model.as_ref().unwrap();
+        let end_lon = self.get_lon(end);
+        let end_lat = self.get_lat(end);
+        state.reset_astar();
+        state.elapsed_times.clear();
+        state.elapsed_times.resize(state.distances.len(), u32::MAX);
+        if !self.are_connected(start, end) {
+            return PathResult {
+                path: None,
+                distance: None,
+                heap_pops: 0,
+            };
+        }
+
+        state.distances[start] = 0;
+        state.elapsed_times[start] = 0;
+        state.astar_queue.push(AStarHeapNode {
+            id: start as u32,
+            g_value: 0,
+            f_value: 0,
+        });
+
+        let mut heap_pops: usize = 0;
+        while let Some(node) = state.astar_queue.pop() {
+            heap_pops += 1;
+
+            // Skip nodes that already have been explored with an earlier arrival
+            if state.distances[node.id as usize] < node.g_value {
+                continue;
+            }
+
+            if node.id == end as u32 {
+                let mut nodes = Vec::new();
+                let mut current_node = end;
+                while current_node != start {
+                    nodes.push(current_node);
+                    current_node = state.parent_nodes[current_node] as usize;
+                }
+                nodes.push(start);
+                return PathResult {
+                    path: Some(nodes),
+                    distance: Some(state.elapsed_times[end]),
+                    heap_pops,
+                };
+            }
+
+            let elapsed_time = state.elapsed_times[node.id as usize];
+            let lon = self.get_lon(node.id as usize);
+            let lat = self.get_lat(node.id as usize);
+            for i in self.offsets[node.id as usize].0 as usize
+                ..self.offsets[node.id as usize + 1].0 as usize
+            {
+                let dest = self.edges[i].destination as usize;
+                if state.restrictions.is_blocked(i, dest) {
+                    continue;
+                }
+                let dest_lon = self.get_lon(dest);
+                let dest_lat = self.get_lat(dest);
+                let travel_time = match self.get_canal(i) {
+                    Some(canal) => Some(canal.transit_time * 3600.0),
+                    None => weather_model.travel_time(
+                        lon,
+                        lat,
+                        dest_lon,
+                        dest_lat,
+                        departure + elapsed_time as i64,
+                    ),
+                };
+                let travel_time = match travel_time {
+                    Some(travel_time) => travel_time,
+                    None => continue,
+                };
+                // Penalty zones only change the order of the search, the weather is sampled at the real time
+                let g_value = node.g_value
+                    + (travel_time * state.restrictions.get_cost_factor(node.id as usize, dest)
+                        + state.restrictions.get_entry_penalty(node.id as usize, dest) as f64
+                            / weather_model.vessel_speed)
+                        .ceil() as u32;
+
+                if g_value < state.distances[dest] {
+                    state.parent_nodes[dest] = node.id;
+                    state.distances[dest] = g_value;
+                    state.elapsed_times[dest] = elapsed_time + travel_time.ceil() as u32;
+
+                    let h_value = if use_heuristic {
+                        (self.heuristic_distance(dest_lon, dest_lat, end_lon, end_lat) as f64
+                            / weather_model.reference_speed) as u32
+                    } else {
+                        0
+                    };
+                    state.astar_queue.push(AStarHeapNode {
+                        id: dest as u32,
+                        g_value,
+                        f_value: g_value + h_value,
+                    });
+                }
+            }
+        }
+
+        // No path found
+        PathResult {
+            path: None,
+            distance: None,
+            heap_pops,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Uniform drift in m/s around the equator between lon -1 and 2
+    fn create_field(u: f64, v: f64) -> VectorField {
+        VectorField::new_from_values(&[
+            (-1.0, -1.0, u, v),
+            (2.0, -1.0, u, v),
+            (-1.0, 1.0, u, v),
+            (2.0, 1.0, u, v),
+        ])
+    }
+
+    #[test]
+    fn speed_over_ground_with_drift() {
+        let speed = |lon1, lat1, lon2, lat2, drift: (f64, f64)| {
+            calculate_speed_over_ground(5.0, lon1, lat1, lon2, lat2, |_, _| drift)
+        };
+        assert_eq!(speed(0.0, 0.0, 1.0, 0.0, (0.0, 0.0)), Some(5.0));
+        // Along track drift adds to the speed, cross drift is compensated by crabbing
+        assert_eq!(speed(0.0, 0.0, 1.0, 0.0, (-2.0, 0.0)), Some(3.0));
+        assert_eq!(speed(0.0, 0.0, 1.0, 0.0, (0.0, 3.0)), Some(4.0));
+        assert_eq!(speed(0.0, 0.0, 1.0, 0.0, (0.0, 5.0)), None);
+        assert_eq!(speed(0.0, 0.0, 1.0, 0.0, (-5.0, 0.0)), None);
+        // Heading east across the antimeridian
+        assert_eq!(speed(179.5, 0.0, -179.5, 0.0, (1.0, 0.0)), Some(6.0));
+        assert_eq!(speed(1.0, 1.0, 1.0, 1.0, (3.0, 3.0)), Some(5.0));
+    }
+
+    #[test]
+    fn weather_travel_time_is_fifo() {
+        // Strong current against the vessel first, then with it
+        let currents = VectorFieldSeries {
+            times: vec![0, 10000],
+            fields: vec![create_field(-4.0, 0.0), create_field(4.0, 0.0)],
+        };
+        let model = WeatherModel::new(10.0, Some(currents), None, 0.0);
+        let travel_time = |departure| model.travel_time(0.0, 0.0, 1.0, 0.0, departure).unwrap();
+
+        let first = travel_time(0);
+        let mut previous_arrival = first;
+        for departure in (500..10000).step_by(500) {
+            let arrival = departure as f64 + travel_time(departure);
+            assert!(arrival >= previous_arrival - 1e-6);
+            previous_arrival = arrival;
+        }
+        // Without the clamp the travel time would be interpolated towards the much shorter one at 10000
+        assert!((travel_time(5000) - (first - 5000.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn parse_timestamps() {
+        assert_eq!(parse_timestamp("1640368800"), Some(1640368800));
+        assert_eq!(parse_timestamp("2021-12-24"), Some(1640304000));
+        assert_eq!(parse_timestamp("2021-12-24T18:00"), Some(1640368800));
+        assert_eq!(parse_timestamp("2021-12-24 18:00:30"), Some(1640368830));
+        assert_eq!(parse_timestamp("2021-12-24T18:00:00Z"), Some(1640368800));
+        assert_eq!(parse_timestamp("2020-02-29"), Some(1582934400));
+        assert_eq!(parse_timestamp("1969-12-31"), Some(-86400));
+        assert_eq!(parse_timestamp("2021-13-01"), None);
+        assert_eq!(parse_timestamp("2021-12"), None);
+        assert_eq!(parse_timestamp("yesterday"), None);
+    }
+}
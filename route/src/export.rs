@@ -0,0 +1,216 @@
+use std::{fmt::Write, str::FromStr};
+
+use crate::{Graph, PathResult};
+
+// Formats used by ECDIS and chart plotters
+pub enum ExportFormat {
+    Gpx,
+    Kml,
+    Rtz,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn get_strings() -> Vec<&'static str> {
+        vec!["gpx", "kml", "rtz", "csv"]
+    }
+
+    pub fn get_content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Gpx => "application/gpx+xml",
+            ExportFormat::Kml => "application/vnd.google-earth.kml+xml",
+            ExportFormat::Rtz => "application/xml",
+            ExportFormat::Csv => "text/csv",
+        }
+    }
+
+    pub fn get_extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Gpx => "gpx",
+            ExportFormat::Kml => "kml",
+            ExportFormat::Rtz => "rtz",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "gpx" => Ok(ExportFormat::Gpx),
+            "kml" => Ok(ExportFormat::Kml),
+            "rtz" => Ok(ExportFormat::Rtz),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => Err(()),
+        }
+    }
+}
+
+// Removes waypoints lying on a straight line between their neighbors, e.g. along rows of the raster
+pub fn simplify_waypoints(waypoints: &[[f64; 2]]) -> Vec<[f64; 2]> {
+    let mut simplified: Vec<[f64; 2]> = Vec::with_capacity(waypoints.len());
+    for waypoint in waypoints.iter() {
+        if simplified.len() >= 2 {
+            let [lon1, lat1] = simplified[simplified.len() - 2];
+            let [lon2, lat2] = simplified[simplified.len() - 1];
+            let cross = (lon2 - lon1) * (waypoint[1] - lat1) - (lat2 - lat1) * (waypoint[0] - lon1);
+            let is_forward =
+                (lon2 - lon1) * (waypoint[0] - lon2) + (lat2 - lat1) * (waypoint[1] - lat2) >= 0.0;
+            if cross.abs() < 1e-9 && is_forward {
+                simplified.pop();
+            }
+        }
+        if simplified.last() != Some(waypoint) {
+            simplified.push(*waypoint);
+        }
+    }
+    simplified
+}
+
+impl Graph {
+    // Waypoints from the start to the end position for the path of a search algorithm, which is stored from
+    // end to start, None if no path was found. Routes of find_path already contain their waypoints.
+    pub fn get_waypoints(
+        &self,
+        path_result: &PathResult,
+        lon1: f64,
+        lat1: f64,
+        lon2: f64,
+        lat2: f64,
+    ) -> Option<Vec<[f64; 2]>> {
+        let mut waypoints = vec![[lon1, lat1]];
+        for node in path_result.path.as_ref()?.iter().rev() {
+            waypoints.push([self.get_lon(*node), self.get_lat(*node)]);
+        }
+        waypoints.push([lon2, lat2]);
+        Some(waypoints)
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Waypoints are given as lon/lat pairs from start to end, straight sections are merged into one leg
+pub fn export_route(waypoints: &[[f64; 2]], name: &str, format: &ExportFormat) -> String {
+    let waypoints = simplify_waypoints(waypoints);
+    let name = escape_xml(name);
+    let mut output = String::new();
+    match format {
+        ExportFormat::Gpx => {
+            output.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+            output.push_str("<gpx version=\"1.1\" creator=\"ship-routing\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n");
+            writeln!(output, "  <rte>\n    <name>{}</name>", name).unwrap();
+            for (i, [lon, lat]) in waypoints.iter().enumerate() {
+                writeln!(
+                    output,
+                    "    <rtept lat=\"{:.6}\" lon=\"{:.6}\"><name>WP{}</name></rtept>",
+                    lat,
+                    lon,
+                    i + 1
+                )
+                .unwrap();
+            }
+            output.push_str("  </rte>\n</gpx>\n");
+        }
+        ExportFormat::Kml => {
+            output.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+            output.push_str("<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n");
+            writeln!(output, "  <Document>\n    <name>{}</name>", name).unwrap();
+            writeln!(output, "    <Placemark>\n      <name>{}</name>", name).unwrap();
+            output.push_str(
+                "      <LineString>\n        <tessellate>1</tessellate>\n        <coordinates>\n",
+            );
+            for [lon, lat] in waypoints.iter() {
+                writeln!(output, "          {:.6},{:.6},0", lon, lat).unwrap();
+            }
+            output.push_str("        </coordinates>\n      </LineString>\n    </Placemark>\n  </Document>\n</kml>\n");
+        }
+        ExportFormat::Rtz => {
+            // IEC 61174 route exchange format, version 1.0
+            output.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+            output.push_str("<route xmlns=\"http://www.cirm.org/RTZ/1/0\" version=\"1.0\">\n");
+            writeln!(output, "  <routeInfo routeName=\"{}\"/>", name).unwrap();
+            output.push_str("  <waypoints>\n");
+            for (i, [lon, lat]) in waypoints.iter().enumerate() {
+                writeln!(
+                    output,
+                    "    <waypoint id=\"{}\" name=\"WP{}\">\n      <position lat=\"{:.6}\" lon=\"{:.6}\"/>\n    </waypoint>",
+                    i + 1,
+                    i + 1,
+                    lat,
+                    lon
+                )
+                .unwrap();
+            }
+            output.push_str("  </waypoints>\n</route>\n");
+        }
+        ExportFormat::Csv => {
+            output.push_str("waypoint,lat,lon\n");
+            for (i, [lon, lat]) in waypoints.iter().enumerate() {
+                writeln!(output, "WP{},{:.6},{:.6}", i + 1, lat, lon).unwrap();
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waypoints_of_path_result() {
+        let graph = Graph {
+            coordinates: vec![[1.0, 1.0], [2.0, 1.0], [3.0, 2.0]],
+            ..Default::default()
+        };
+        let path_result = PathResult {
+            path: Some(vec![2, 1, 0]),
+            distance: Some(1),
+            heap_pops: 0,
+        };
+        assert_eq!(
+            graph.get_waypoints(&path_result, 0.5, 1.0, 3.5, 2.0),
+            Some(vec![
+                [0.5, 1.0],
+                [1.0, 1.0],
+                [2.0, 1.0],
+                [3.0, 2.0],
+                [3.5, 2.0]
+            ])
+        );
+        let no_path = PathResult {
+            path: None,
+            distance: None,
+            heap_pops: 0,
+        };
+        assert_eq!(graph.get_waypoints(&no_path, 0.5, 1.0, 3.5, 2.0), None);
+    }
+
+    #[test]
+    fn simplify_straight_sections() {
+        let waypoints = [
+            [0.0, 0.0],
+            [1.0, 0.0],
+            [2.0, 0.0],
+            [2.0, 0.0],
+            [3.0, 1.0],
+            [4.0, 2.0],
+            [4.0, 3.0],
+        ];
+        assert_eq!(
+            simplify_waypoints(&waypoints),
+            vec![[0.0, 0.0], [2.0, 0.0], [4.0, 2.0], [4.0, 3.0]]
+        );
+        // Turning back on the same line keeps the turning point
+        assert_eq!(
+            simplify_waypoints(&[[0.0, 0.0], [2.0, 0.0], [1.0, 0.0]]),
+            vec![[0.0, 0.0], [2.0, 0.0], [1.0, 0.0]]
+        );
+    }
+}
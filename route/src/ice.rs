@@ -0,0 +1,229 @@
+use std::{fs, path::Path, str::FromStr};
+
+use crate::environment::parse_timestamp;
+use crate::raster::Raster;
+use crate::{Graph, Restrictions};
+
+// Cells with a lower ice concentration are treated as open water
+pub const ICE_EDGE_CONCENTRATION: f64 = 0.15;
+const CONCENTRATION_BANDS: usize = 10;
+// Dated masks are used for queries up to this many days before or after their date
+pub const DATED_MASK_VALIDITY_DAYS: i64 = 7;
+
+// Finnish-Swedish ice classes and IACS polar classes
+#[derive(Default, Clone, Copy, PartialEq)]
+pub enum IceClass {
+    #[default]
+    None,
+    C1,
+    B1,
+    A1,
+    A1Super,
+    PC7,
+    PC6,
+    PC5,
+    PC4,
+    PC3,
+    PC2,
+    PC1,
+}
+
+impl IceClass {
+    pub fn get_strings() -> Vec<&'static str> {
+        vec![
+            "None", "1C", "1B", "1A", "1ASuper", "PC7", "PC6", "PC5", "PC4", "PC3", "PC2", "PC1",
+        ]
+    }
+
+    // Highest ice concentration the vessel may enter and the additional costs when sailing through
+    // complete ice cover
+    pub fn get_limits(&self) -> (f64, f64) {
+        match self {
+            IceClass::None => (ICE_EDGE_CONCENTRATION, 1.0),
+            IceClass::C1 => (0.3, 1.0),
+            IceClass::B1 => (0.4, 1.0),
+            IceClass::A1 => (0.5, 1.0),
+            IceClass::A1Super => (0.7, 0.8),
+            IceClass::PC7 => (0.8, 0.8),
+            IceClass::PC6 => (0.85, 0.7),
+            IceClass::PC5 => (0.9, 0.6),
+            IceClass::PC4 => (0.95, 0.5),
+            IceClass::PC3 => (1.0, 0.4),
+            IceClass::PC2 => (1.0, 0.3),
+            IceClass::PC1 => (1.0, 0.2),
+        }
+    }
+}
+
+impl FromStr for IceClass {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace(' ', "").as_str() {
+            "" | "none" => Ok(IceClass::None),
+            "1c" => Ok(IceClass::C1),
+            "1b" => Ok(IceClass::B1),
+            "1a" => Ok(IceClass::A1),
+            "1asuper" | "1as" => Ok(IceClass::A1Super),
+            "pc7" => Ok(IceClass::PC7),
+            "pc6" => Ok(IceClass::PC6),
+            "pc5" => Ok(IceClass::PC5),
+            "pc4" => Ok(IceClass::PC4),
+            "pc3" => Ok(IceClass::PC3),
+            "pc2" => Ok(IceClass::PC2),
+            "pc1" => Ok(IceClass::PC1),
+            _ => Err(()),
+        }
+    }
+}
+
+// Sea ice concentration rasters, either for a month of every year or valid from a date on
+#[derive(Default)]
+pub struct IceMasks {
+    pub monthly: Vec<Option<Raster>>, // Index 0 is January
+    pub dated: Vec<(i64, Raster)>,    // Sorted by date
+}
+
+impl IceMasks {
+    // Each line contains a month (1-12) or a date followed by the path of an asc file, e.g. "3,ice_march.asc"
+    // or "2021-12-24,ice_20211224.asc". Relative paths are resolved from the directory of the list file.
+    // Concentrations are fractions, unless "percent" is appended to the line, e.g. "3,ice_march.asc,percent".
+    pub fn new_from_listfile(filename: &str) -> Self {
+        println!("Reading ice masks from list file: {}", filename);
+        let directory = Path::new(filename).parent().unwrap();
+        let mut ice_masks = IceMasks {
+            monthly: (0..12).map(|_| None).collect(),
+            dated: Vec::new(),
+        };
+        for line in fs::read_to_string(filename).unwrap().lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut parts = line.split(',').map(|p| p.trim());
+            let time = parts.next().unwrap();
+            let file = parts.next().unwrap();
+            let mut raster = Raster::new_from_ascfile(directory.join(file).to_str().unwrap());
+            match parts.next() {
+                None | Some("fraction") => {}
+                Some("percent") => raster.values.iter_mut().for_each(|v| *v /= 100.0),
+                Some(unit) => panic!("Unknown ice concentration unit: {}", unit),
+            }
+            match time.parse::<usize>() {
+                Ok(month) if (1..=12).contains(&month) => {
+                    ice_masks.monthly[month - 1] = Some(raster)
+                }
+                _ => ice_masks
+                    .dated
+                    .push((parse_timestamp(time).unwrap(), raster)),
+            }
+        }
+        ice_masks.dated.sort_by_key(|(time, _)| *time);
+        println!(
+            "Read {} monthly and {} dated ice masks",
+            ice_masks.monthly.iter().flatten().count(),
+            ice_masks.dated.len()
+        );
+        ice_masks
+    }
+
+    // Dated mask closest to the time within its validity, otherwise the mask of the month
+    pub fn get_mask(&self, time: i64) -> Option<&Raster> {
+        match self
+            .dated
+            .iter()
+            .filter(|(t, _)| (t - time).abs() <= DATED_MASK_VALIDITY_DAYS * 86400)
+            .min_by_key(|(t, _)| (t - time).abs())
+        {
+            Some((_, raster)) => Some(raster),
+            None => self.monthly[get_month(time) - 1].as_ref(),
+        }
+    }
+}
+
+// Month (1-12) of a unix timestamp
+pub fn get_month(time: i64) -> usize {
+    // Inverse of the day calculation in parse_timestamp, with years starting in March
+    let days = time.div_euclid(86400) + 719468;
+    let era = if days >= 0 { days } else { days - 146096 } / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    (if month < 10 { month + 3 } else { month - 9 }) as usize
+}
+
+impl Graph {
+    // Blocks nodes with more ice than the ice class allows and makes the remaining ice covered nodes more
+    // expensive depending on their ice concentration
+    pub fn apply_ice(&self, time: i64, ice_class: IceClass, restrictions: &mut Restrictions) {
+        let raster = match self.ice_masks.as_ref().and_then(|m| m.get_mask(time)) {
+            Some(raster) => raster,
+            None => return,
+        };
+        let (max_concentration, extra_costs) = ice_class.get_limits();
+
+        let node_count = self.offsets.len() - 1;
+        let first_band = restrictions.zone_costs.len();
+        for node in 0..node_count {
+            if self.offsets[node] == self.offsets[node + 1] {
+                continue;
+            }
+            let concentration = match raster.sample(self.get_lon(node), self.get_lat(node)) {
+                Some(c) => c,
+                None => continue,
+            };
+            if concentration < ICE_EDGE_CONCENTRATION {
+                continue;
+            }
+            if concentration >= max_concentration && max_concentration < 1.0 {
+                restrictions.block_node(node, node_count);
+                continue;
+            }
+
+            // Penalty zones are used for the concentration bands, so nodes inside of a more expensive
+            // penalty zone keep it
+            if restrictions.zone_costs.len() == first_band {
+                restrictions
+                    .zone_costs
+                    .extend((0..CONCENTRATION_BANDS).map(|b| {
+                        let band_concentration = (b + 1) as f64 / CONCENTRATION_BANDS as f64;
+                        (1.0 + band_concentration * extra_costs, 0)
+                    }));
+            }
+            if restrictions.node_zones.is_empty() {
+                restrictions.node_zones = vec![0; node_count];
+            }
+            let band = first_band
+                + ((concentration * CONCENTRATION_BANDS as f64) as usize)
+                    .min(CONCENTRATION_BANDS - 1);
+            let current_zone = restrictions.node_zones[node];
+            if current_zone == 0
+                || restrictions.zone_costs[current_zone as usize - 1].0
+                    < restrictions.zone_costs[band].0
+            {
+                restrictions.node_zones[node] = band as u16 + 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn month_of_timestamps() {
+        assert_eq!(get_month(0), 1);
+        assert_eq!(get_month(-1), 12);
+        assert_eq!(get_month(1582934400), 2); // 2020-02-29
+        assert_eq!(get_month(1583020800), 3); // 2020-03-01
+        for month in 1..=12 {
+            let date = format!("2024-{:02}-01T00:00", month);
+            assert_eq!(get_month(parse_timestamp(&date).unwrap()), month);
+            assert_eq!(
+                get_month(parse_timestamp(&date).unwrap() - 1),
+                (month + 10) % 12 + 1
+            );
+        }
+    }
+}
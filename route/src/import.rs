@@ -0,0 +1,289 @@
+use std::fs;
+
+use serde_json::Value;
+
+use crate::zones::{self, Zone};
+use crate::{AlgorithmState, ExecutionType, Graph, QueryOptions, METERS_PER_NAUTICAL_MILE};
+
+#[derive(serde::Serialize)]
+pub struct LegValidation {
+    pub leg: usize, // Starting at 1
+    pub from: [f64; 2],
+    pub to: [f64; 2],
+    pub distance: f64, // Nautical miles
+    pub crosses_coastline: bool,
+    pub land_cells: Vec<[f64; 2]>, // Positions of the raster nodes on the leg which aren't water
+}
+
+#[derive(serde::Serialize)]
+pub struct RouteValidation {
+    pub distance: f64,                 // Nautical miles
+    pub optimal_distance: Option<f64>, // Nautical miles, None if no route was found
+    pub detour: Option<f64>, // Additional distance compared to the optimal route in percent
+    pub is_safe: bool, // No leg crosses the coastlines, or no leg touches land cells if there are no coastlines
+    pub legs: Vec<LegValidation>,
+}
+
+// Reads the waypoints of a route (lon/lat pairs from start to end) from a GPX, RTZ or GeoJSON file
+pub fn import_route_file(filename: &str) -> Result<Vec<[f64; 2]>, String> {
+    println!("Importing route from file: {}", filename);
+    import_route(&fs::read_to_string(filename).map_err(|e| e.to_string())?)
+}
+
+pub fn import_route(content: &str) -> Result<Vec<[f64; 2]>, String> {
+    let waypoints = if content.trim_start().starts_with('{') {
+        parse_geojson(content)?
+    } else if content.contains("<gpx") {
+        // Routes are preferred over tracks and single waypoints
+        ["rtept", "trkpt", "wpt"]
+            .iter()
+            .map(|tag| parse_xml_positions(content, tag))
+            .find(|w| !matches!(w, Ok(w) if w.is_empty()))
+            .unwrap_or(Ok(Vec::new()))?
+    } else if content.contains("<route") {
+        parse_xml_positions(content, "position")?
+    } else {
+        return Err("Unknown route format, expected GPX, RTZ or GeoJSON".to_string());
+    };
+
+    if waypoints.len() < 2 {
+        return Err("Routes need at least two waypoints".to_string());
+    }
+    Ok(waypoints)
+}
+
+// Coordinates of all LineStrings in the order of their appearance
+fn parse_geojson(content: &str) -> Result<Vec<[f64; 2]>, String> {
+    let value: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let mut waypoints = Vec::new();
+    collect_line_coordinates(&value, &mut waypoints)?;
+    Ok(waypoints)
+}
+
+fn collect_line_coordinates(value: &Value, waypoints: &mut Vec<[f64; 2]>) -> Result<(), String> {
+    match value["type"].as_str() {
+        Some("FeatureCollection") => {
+            for feature in value["features"]
+                .as_array()
+                .ok_or("FeatureCollection without features")?
+            {
+                collect_line_coordinates(feature, waypoints)?;
+            }
+        }
+        Some("Feature") => collect_line_coordinates(&value["geometry"], waypoints)?,
+        Some("LineString") => parse_line(&value["coordinates"], waypoints)?,
+        Some("MultiLineString") => {
+            for line in value["coordinates"]
+                .as_array()
+                .ok_or("Invalid MultiLineString coordinates")?
+            {
+                parse_line(line, waypoints)?;
+            }
+        }
+        Some(_) => {}
+        None => return Err("Missing GeoJSON type".to_string()),
+    }
+    Ok(())
+}
+
+fn parse_line(coordinates: &Value, waypoints: &mut Vec<[f64; 2]>) -> Result<(), String> {
+    for point in coordinates
+        .as_array()
+        .ok_or("Invalid LineString coordinates")?
+    {
+        match (point[0].as_f64(), point[1].as_f64()) {
+            (Some(lon), Some(lat)) => waypoints.push([lon, lat]),
+            _ => return Err("Invalid LineString coordinate".to_string()),
+        }
+    }
+    Ok(())
+}
+
+// Positions of all elements with the tag which have lat and lon attributes
+fn parse_xml_positions(content: &str, tag: &str) -> Result<Vec<[f64; 2]>, String> {
+    let mut waypoints = Vec::new();
+    let start_tag = format!("<{}", tag);
+    for (start, _) in content.match_indices(&start_tag) {
+        let element = &content[start + start_tag.len()..];
+        if !element.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let element = &element[..element.find('>').ok_or("Unclosed XML element")?];
+        match (
+            get_xml_attribute(element, "lon"),
+            get_xml_attribute(element, "lat"),
+        ) {
+            (Some(lon), Some(lat)) => waypoints.push([lon, lat]),
+            _ => return Err(format!("Element {} without lat and lon", tag)),
+        }
+    }
+    Ok(waypoints)
+}
+
+// Attributes are name="value" or name='value' pairs, whitespace around the '=' is allowed and values may
+// contain '=' or the other quote
+fn get_xml_attribute(element: &str, name: &str) -> Option<f64> {
+    let mut rest = element;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if rest.is_empty() {
+            return None;
+        }
+        let key_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=')
+            .unwrap_or(rest.len());
+        let key = &rest[..key_end];
+        rest = rest[key_end..].trim_start();
+        let value = match rest.strip_prefix('=') {
+            Some(value) => value.trim_start(),
+            None => continue, // Attribute without a value
+        };
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let end = value[1..].find(quote)? + 1;
+        if key == name {
+            return value[1..end].trim().parse().ok();
+        }
+        rest = &value[end + 1..];
+    }
+}
+
+impl Graph {
+    // Raster node closest to the position, positions outside of a regional raster get the node at its border
+    pub fn get_node_at(&self, lon: f64, lat: f64) -> usize {
+        if !self.is_raster() {
+            return self.find_nearest_indexed_node(lon, lat);
+        }
+        let column = ((lon - self.bounding_box.0) / self.get_lon_step())
+            .round()
+            .max(0.0) as usize;
+        let column = if self.is_global() {
+            column % self.raster_columns_count
+        } else {
+            column.min(self.raster_columns_count - 1)
+        };
+        let row = (((self.bounding_box.3 - lat) / self.get_lat_step())
+            .round()
+            .max(0.0) as usize)
+            .min(self.raster_rows_count - 1);
+        row * self.raster_columns_count + column
+    }
+
+    // Checks every leg against the raster of the graph and the coastline polygons (if given) and compares
+    // the length of the route with the route between its first and last waypoint computed by the algorithm
+    pub fn validate_route(
+        &self,
+        waypoints: &[[f64; 2]],
+        coastlines: &[Zone],
+        execution_type: &ExecutionType,
+        state: &mut AlgorithmState,
+    ) -> RouteValidation {
+        let mut legs = Vec::new();
+        for (i, leg) in waypoints.windows(2).enumerate() {
+            let (length, samples) = zones::sample_segment(leg[0], leg[1]);
+
+            let mut land_nodes: Vec<usize> = Vec::new();
+            for [lon, lat] in samples.iter() {
+                let node = self.get_node_at(*lon, *lat);
+                if self.offsets[node] == self.offsets[node + 1] && !land_nodes.contains(&node) {
+                    land_nodes.push(node);
+                }
+            }
+
+            legs.push(LegValidation {
+                leg: i + 1,
+                from: leg[0],
+                to: leg[1],
+                distance: length / METERS_PER_NAUTICAL_MILE,
+                crosses_coastline: coastlines
+                    .iter()
+                    .any(|c| c.intersects_segment(leg[0][0], leg[0][1], leg[1][0], leg[1][1])),
+                land_cells: land_nodes
+                    .iter()
+                    .map(|n| [self.get_lon(*n), self.get_lat(*n)])
+                    .collect(),
+            });
+        }
+
+        let distance = legs.iter().map(|l| l.distance).sum();
+        let first = waypoints[0];
+        let last = waypoints[waypoints.len() - 1];
+        let optimal_distance = self
+            .find_path(
+                first[0],
+                first[1],
+                last[0],
+                last[1],
+                execution_type,
+                &QueryOptions::default(),
+                state,
+            )
+            .ok()
+            .map(|r| r.distance / METERS_PER_NAUTICAL_MILE);
+
+        // The raster is too coarse for ports and narrow passages, so the coastlines are preferred if available
+        let is_safe = if coastlines.is_empty() {
+            legs.iter().all(|l| l.land_cells.is_empty())
+        } else {
+            legs.iter().all(|l| !l.crosses_coastline)
+        };
+
+        RouteValidation {
+            distance,
+            optimal_distance,
+            detour: optimal_distance
+                .filter(|d| *d > 0.0)
+                .map(|d| (distance / d - 1.0) * 100.0),
+            is_safe,
+            legs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn import_gpx_rtz_and_geojson() {
+        let gpx = r#"<?xml version="1.0"?><gpx version="1.1"><wpt lat="9" lon="9"/><rte>
+            <rtept lat="1.5" lon="2.5"><name>WP1</name></rtept>
+            <rtept lon="3" lat="4"/></rte></gpx>"#;
+        assert_eq!(import_route(gpx), Ok(vec![[2.5, 1.5], [3.0, 4.0]]));
+
+        let rtz = r#"<route version="1.0"><waypoints>
+            <waypoint id="1"><position lat="10" lon="20"/></waypoint>
+            <waypoint id="2"><position lat="11" lon="21"/></waypoint></waypoints></route>"#;
+        assert_eq!(import_route(rtz), Ok(vec![[20.0, 10.0], [21.0, 11.0]]));
+
+        let geojson = r#"{"type": "Feature", "geometry": {"type": "LineString",
+            "coordinates": [[1, 2], [3, 4], [5, 6]]}}"#;
+        assert_eq!(
+            import_route(geojson),
+            Ok(vec![[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]])
+        );
+    }
+
+    #[test]
+    fn xml_attributes() {
+        let element = r#" name="a=b 'lat'" lat = '1.5' lon="-2" "#;
+        assert_eq!(get_xml_attribute(element, "lat"), Some(1.5));
+        assert_eq!(get_xml_attribute(element, "lon"), Some(-2.0));
+        assert_eq!(get_xml_attribute(element, "name"), None);
+        assert_eq!(get_xml_attribute(element, "b"), None);
+        assert_eq!(
+            get_xml_attribute(r#" data-lat="3" lat="4"/"#, "lat"),
+            Some(4.0)
+        );
+        assert_eq!(get_xml_attribute(r#" lat="4"#, "lat"), None);
+    }
+
+    #[test]
+    fn import_invalid_routes() {
+        assert!(import_route("1,2\n3,4").is_err());
+        assert!(import_route(r#"<gpx><rte><rtept lat="1" lon="2"/></rte></gpx>"#).is_err());
+        assert!(import_route(r#"<gpx><rte><rtept lat="1"/><rtept lat="2"/></rte></gpx>"#).is_err());
+        assert!(
+            import_route(r#"{"type": "LineString", "coordinates": [[1, "a"], [2, 3]]}"#).is_err()
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use rand::Rng;
+
+use crate::components::NO_COMPONENT;
+use crate::{AlgorithmState, Graph};
+
+// Further issues of the same check are only counted
+const MAX_REPORTED_ISSUES: usize = 10;
+// Meters, edge distances are rounded down to whole meters
+const DISTANCE_TOLERANCE: u32 = 1;
+
+// Issues found by one check of the graph
+pub struct IntegrityCheck {
+    pub name: &'static str,
+    pub issues: Vec<String>, // At most MAX_REPORTED_ISSUES
+    pub issue_count: usize,
+}
+
+impl IntegrityCheck {
+    fn new(name: &'static str) -> Self {
+        IntegrityCheck {
+            name,
+            issues: Vec::new(),
+            issue_count: 0,
+        }
+    }
+
+    fn report(&mut self, issue: String) {
+        if self.issues.len() < MAX_REPORTED_ISSUES {
+            self.issues.push(issue);
+        }
+        self.issue_count += 1;
+    }
+}
+
+impl Graph {
+    // Checks the structural invariants of the graph. The distances of the given number of randomly sampled
+    // shortcut edges are compared to the shortest path on the raster edges.
+    pub fn check_integrity(&self, shortcut_samples: usize) -> Vec<IntegrityCheck> {
+        let node_count = self.node_count();
+        let mut checks = Vec::new();
+
+        let mut check = IntegrityCheck::new("Offsets");
+        if self.offsets.len() != node_count + 1 {
+            check.report(format!(
+                "{} offsets for {} nodes, expected {}",
+                self.offsets.len(),
+                node_count,
+                node_count + 1
+            ));
+            // The other checks index the offsets by node
+            checks.push(check);
+            return checks;
+        }
+        for node in 0..node_count {
+            if self.offsets[node].0 > self.offsets[node + 1].0 {
+                check.report(format!(
+                    "Offset of node {} is larger than the offset of the next node",
+                    node
+                ));
+            }
+        }
+        if self.offsets[node_count].0 as usize != self.edges.len() {
+            check.report(format!(
+                "Last offset is {} but there are {} edges",
+                self.offsets[node_count].0,
+                self.edges.len()
+            ));
+        }
+        let is_valid = check.issue_count == 0;
+        checks.push(check);
+        if !is_valid {
+            return checks;
+        }
+
+        let is_water = |node: usize| self.offsets[node] != self.offsets[node + 1];
+        let mut destinations = IntegrityCheck::new("Edge destinations");
+        let mut land = IntegrityCheck::new("Edges to land nodes");
+        let mut symmetry = IntegrityCheck::new("Symmetry of raster edges");
+        let mut distances = IntegrityCheck::new("Edge distances");
+        let mut shortcut_edges = Vec::new();
+        for node in 0..node_count {
+            for e in self.offsets[node].0 as usize..self.offsets[node + 1].0 as usize {
+                let edge = self.edges[e];
+                let dest = edge.destination as usize;
+                if dest >= node_count || dest == node {
+                    destinations.report(format!("Edge {} of node {} points to {}", e, node, dest));
+                    continue;
+                }
+                if !is_water(dest) {
+                    land.report(format!("Edge {} from {} to land node {}", e, node, dest));
+                }
+
+                let geodesic = Self::calculate_distance(
+                    self.get_lon(node),
+                    self.get_lat(node),
+                    self.get_lon(dest),
+                    self.get_lat(dest),
+                );
+                if !self.is_raster_neighbor(node, dest) {
+                    // Shortcut edges can't be shorter than the great circle distance
+                    if edge.distance + DISTANCE_TOLERANCE < geodesic {
+                        distances.report(format!(
+                            "Shortcut edge {} from {} to {} is {} m long, the great circle distance is {} m",
+                            e, node, dest, edge.distance, geodesic
+                        ));
+                    }
+                    shortcut_edges.push(e);
+                    continue;
+                }
+                if edge.distance.abs_diff(geodesic) > DISTANCE_TOLERANCE {
+                    distances.report(format!(
+                        "Edge {} from {} to {} is {} m long, expected {} m",
+                        e, node, dest, edge.distance, geodesic
+                    ));
+                }
+                let has_reverse_edge = self.edges
+                    [self.offsets[dest].0 as usize..self.offsets[dest + 1].0 as usize]
+                    .iter()
+                    .any(|r| r.destination as usize == node && r.distance == edge.distance);
+                if !has_reverse_edge {
+                    symmetry.report(format!(
+                        "Edge {} from {} to {} has no reverse edge",
+                        e, node, dest
+                    ));
+                }
+            }
+        }
+        checks.push(destinations);
+        checks.push(land);
+        checks.push(symmetry);
+        checks.push(distances);
+
+        let mut node_data = IntegrityCheck::new("Node data");
+        for (name, length) in [
+            ("depths", self.depths.len()),
+            ("sea areas", self.sea_areas.len()),
+            ("components", self.components.len()),
+        ] {
+            if length != 0 && length != node_count {
+                node_data.report(format!("{} {} for {} nodes", length, name, node_count));
+            }
+        }
+        if self.sea_areas.len() == node_count {
+            if let Some(node) =
+                (0..node_count).find(|n| self.sea_areas[*n] as usize > self.sea_area_names.len())
+            {
+                node_data.report(format!("Node {} has an unknown sea area", node));
+            }
+        }
+        if self.components.len() == node_count {
+            for node in 0..node_count {
+                if is_water(node) == (self.components[node] == NO_COMPONENT) {
+                    node_data.report(format!("Node {} has a wrong component", node));
+                }
+                for e in self.offsets[node].0 as usize..self.offsets[node + 1].0 as usize {
+                    let dest = self.edges[e].destination as usize;
+                    if dest < node_count && self.components[dest] != self.components[node] {
+                        node_data.report(format!(
+                            "Edge {} connects the components of {} and {}",
+                            e, node, dest
+                        ));
+                    }
+                }
+            }
+        }
+        checks.push(node_data);
+
+        // Nodes belong to the first rectangle they are inside of
+        let mut rectangles = IntegrityCheck::new("Shortcut rectangles");
+        for node in 0..node_count {
+            let expected = self
+                .shortcut_rectangles
+                .iter()
+                .position(|rect| self.is_node_inside_rect(node, rect));
+            if self.offsets[node].1 != expected {
+                rectangles.report(format!(
+                    "Node {} has rectangle {:?}, expected {:?}",
+                    node, self.offsets[node].1, expected
+                ));
+            }
+        }
+        checks.push(rectangles);
+
+        // Shortcut edges have the length of the shortest path without shortcut edges
+        let mut shortcuts = IntegrityCheck::new("Shortcut distances");
+        if !shortcut_edges.is_empty() && shortcut_samples > 0 {
+            let mut state = AlgorithmState::new(node_count);
+            for e in shortcut_edges.iter() {
+                state.restrictions.block_edge(*e, self.edges.len());
+            }
+
+            let mut rng = rand::thread_rng();
+            let sampled_edges: Vec<usize> = if shortcut_samples >= shortcut_edges.len() {
+                shortcut_edges
+            } else {
+                (0..shortcut_samples)
+                    .map(|_| shortcut_edges[rng.gen_range(0..shortcut_edges.len())])
+                    .collect()
+            };
+            for e in sampled_edges {
+                let source = self.offsets.partition_point(|o| o.0 as usize <= e) - 1;
+                let edge = self.edges[e];
+                let result = self.bi_dijkstra(source, edge.destination as usize, &mut state);
+                match result.distance {
+                    Some(distance) if distance.abs_diff(edge.distance) <= DISTANCE_TOLERANCE => {}
+                    Some(distance) => shortcuts.report(format!(
+                        "Shortcut edge {} from {} to {} is {} m long, the shortest path is {} m",
+                        e, source, edge.destination, edge.distance, distance
+                    )),
+                    None => shortcuts.report(format!(
+                        "Shortcut edge {} from {} to {} has no path without shortcuts",
+                        e, source, edge.destination
+                    )),
+                }
+            }
+        }
+        checks.push(shortcuts);
+
+        checks
+    }
+}
@@ -1,15 +1,69 @@
 use std::str::FromStr;
-use std::time::Instant;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use std::{
     cmp::Ordering,
-    collections::BinaryHeap,
+    collections::{BinaryHeap, HashMap},
     fs::File,
     io::{BufReader, BufWriter},
 };
 
 use rand::Rng;
 
+pub mod canals;
+pub mod coastlines;
+pub mod components;
+pub mod corridor;
+pub mod emissions;
+pub mod environment;
+pub mod export;
+pub mod ice;
+pub mod import;
+pub mod integrity;
+pub mod ports;
+pub mod raster;
+pub mod sea_areas;
+pub mod spatial_index;
+pub mod zones;
+
+use canals::{Canal, CanalTransit};
+use coastlines::CoastlineIndex;
+use corridor::DEFAULT_CORRIDOR_WIDTH;
+use emissions::{Emissions, FuelModel};
+use environment::{CostModel, WeatherModel};
+use ice::{IceClass, IceMasks};
+use sea_areas::SeaAreaDistance;
+use spatial_index::SpatialIndex;
+use zones::Zone;
+
 const FACTOR: f64 = 10_000_000.0;
+pub const METERS_PER_NAUTICAL_MILE: f64 = 1852.0;
+pub const UNKNOWN_DEPTH: i16 = i16::MAX;
+pub const GLOBAL_BOUNDING_BOX: (f64, f64, f64, f64) = (-180.0, -90.0, 180.0, 90.0);
+pub const DEFAULT_SNAP_RADIUS: f64 = 100.0 * METERS_PER_NAUTICAL_MILE;
+// Written in front of the graph in binary files, the version has to be increased whenever the serialized
+// fields of the graph change
+const GRAPH_FILE_MAGIC: [u8; 4] = *b"GRPH";
+pub const GRAPH_FORMAT_VERSION: u32 = 1;
+// Row and column offsets of the raster neighbors, the first 4, 8 or 16 are connected depending on the
+// connectivity of the graph: axis-aligned, diagonal and knight moves
+pub const RASTER_NEIGHBOR_OFFSETS: [(isize, isize); 16] = [
+    (-1, 0),
+    (1, 0),
+    (0, 1),
+    (0, -1),
+    (-1, -1),
+    (-1, 1),
+    (1, -1),
+    (1, 1),
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
 
 pub enum ExecutionType {
     Dijkstra,
@@ -95,14 +149,39 @@ impl PartialOrd for AStarHeapNode {
     }
 }
 
-// Graph starts at top left, outer arrays are rows
-#[derive(serde::Serialize, serde::Deserialize)]
+// Graph starts at top left, outer arrays are rows. Graphs with explicit coordinates aren't a raster,
+// their nodes can be anywhere and the raster counts are zero
+#[derive(serde::Serialize, serde::Deserialize, Default)]
 pub struct Graph {
     pub offsets: Vec<(u32, Option<usize>)>, // Holds offset to edges and a bool determining if the node is inside a shortcut rectangle
     pub edges: Vec<Edge>,
     pub raster_columns_count: usize,
     pub raster_rows_count: usize,
+    pub bounding_box: (f64, f64, f64, f64), // min lon, min lat, max lon, max lat of the raster
+    pub coordinates: Vec<[f64; 2]>,         // Lon and lat of each node, empty for raster graphs
+    pub connectivity: u8, // Number of raster neighbors each node is connected to, see RASTER_NEIGHBOR_OFFSETS
     pub shortcut_rectangles: Vec<(usize, usize, usize, usize)>,
+    pub depths: Vec<i16>, // Water depth in meters for each node, empty if the graph was created without bathymetry
+    pub sea_areas: Vec<u16>, // Sea area index + 1 for each node, 0 if outside. Empty if the graph was created without sea areas
+    pub sea_area_names: Vec<String>,
+    pub components: Vec<u32>, // Connected component of each node, see label_components
+    pub largest_component: u32,
+    #[serde(skip)]
+    pub cost_model: Option<CostModel>, // Set if edge distances were replaced by travel times
+    #[serde(skip)]
+    pub weather_model: Option<WeatherModel>,
+    #[serde(skip)]
+    pub canals: Vec<Canal>,
+    #[serde(skip)]
+    pub canal_edges: HashMap<usize, usize>, // Index of the canal of each canal edge
+    #[serde(skip)]
+    pub ice_masks: Option<IceMasks>,
+    #[serde(skip)]
+    pub fuel_model: Option<FuelModel>,
+    #[serde(skip)]
+    pub coastlines: CoastlineIndex, // Segments of the land polygons, used for the first and last mile
+    #[serde(skip)]
+    pub spatial_index: Option<SpatialIndex>, // Set for graphs with explicit coordinates, see build_spatial_index
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Copy, Clone)]
@@ -111,52 +190,195 @@ pub struct Edge {
     pub distance: u32,
 }
 
+// Per query settings for find_path
+#[derive(Default, Clone)]
+pub struct QueryOptions {
+    // Unix timestamp, searches for the earliest arrival if a weather model is set
+    pub departure: Option<i64>,
+    pub exclusion_zones: Vec<Zone>,
+    pub penalty_zones: Vec<Zone>,
+    // Nodes shallower than draft plus under keel clearance (in meters) are avoided
+    pub draft: Option<f64>,
+    pub under_keel_clearance: f64,
+    // Canals are only used if they aren't forbidden and the vessel dimensions (in meters) fit
+    pub forbidden_canals: Vec<String>,
+    pub beam: Option<f64>,
+    pub air_draft: Option<f64>,
+    // Ice masks of the departure date (or the current date) are applied depending on the ice class
+    pub ice_class: IceClass,
+    // Routes through Emission Control Areas become more expensive if a fuel model is set
+    pub minimize_fuel_cost: bool,
+    // Maximum distance in meters between the given positions and their nodes, DEFAULT_SNAP_RADIUS if not set
+    pub snap_radius: Option<f64>,
+    // Nodes the positions were snapped to before, e.g. of ports, which skips the nearest node search
+    pub start_node: Option<usize>,
+    pub end_node: Option<usize>,
+    // Route on a coarse graph, only nodes within corridor_width (in meters, DEFAULT_CORRIDOR_WIDTH if not
+    // set) of it are searched. Empty if the whole graph is searched, see find_path_in_corridor
+    pub corridor: Vec<[f64; 2]>,
+    pub corridor_width: Option<f64>,
+}
+
+pub struct Route {
+    pub geojson: GEOJson<Vec<[f64; 2]>>,
+    pub waypoints: Vec<[f64; 2]>, // From start to end
+    pub distance: f64,
+    pub travel_time: Option<f64>,
+    pub zone_distances: Vec<ZoneDistance>,
+    pub canal_transits: Vec<CanalTransit>,
+    pub emissions: Option<Emissions>,
+    pub sea_areas: Vec<SeaAreaDistance>, // From start to end
+    pub start_snap_distance: f64,        // Meters between the start position and its node
+    pub end_snap_distance: f64,
+}
+
+#[derive(serde::Serialize)]
+pub struct ZoneDistance {
+    pub name: String,
+    pub distance: f64, // Nautical miles
+}
+
 pub struct PathResult {
     pub path: Option<Vec<usize>>,
     pub distance: Option<u32>,
     pub heap_pops: usize,
 }
 
+// Per query modifications of the graph which are respected by all search algorithms
+#[derive(Default)]
+pub struct Restrictions {
+    pub blocked_nodes: Vec<bool>,       // Empty if no node is blocked
+    pub blocked_edges: Vec<bool>,       // Empty if no edge is blocked
+    pub corridor_nodes: Vec<bool>, // Nodes inside of the corridor, empty if the query has no corridor
+    pub disabled_rectangles: Vec<bool>, // Shortcut rectangles whose shortcut edges can't be used
+    pub node_zones: Vec<u16>, // Penalty zone index + 1 for each node, 0 if outside. Empty if there are no penalty zones
+    pub zone_costs: Vec<(f64, u32)>, // Cost factor and entry penalty in meters for each penalty zone
+}
+
+impl Restrictions {
+    pub fn is_empty(&self) -> bool {
+        self.blocked_nodes.is_empty()
+            && self.blocked_edges.is_empty()
+            && self.corridor_nodes.is_empty()
+            && self.node_zones.is_empty()
+    }
+
+    pub fn block_node(&mut self, node: usize, node_count: usize) {
+        if self.blocked_nodes.is_empty() {
+            self.blocked_nodes = vec![false; node_count];
+        }
+        self.blocked_nodes[node] = true;
+    }
+
+    pub fn block_edge(&mut self, edge: usize, edge_count: usize) {
+        if self.blocked_edges.is_empty() {
+            self.blocked_edges = vec![false; edge_count];
+        }
+        self.blocked_edges[edge] = true;
+    }
+
+    pub fn is_node_blocked(&self, node: usize) -> bool {
+        (!self.blocked_nodes.is_empty() && self.blocked_nodes[node])
+            || (!self.corridor_nodes.is_empty() && !self.corridor_nodes[node])
+    }
+
+    pub fn is_edge_blocked(&self, edge: usize) -> bool {
+        !self.blocked_edges.is_empty() && self.blocked_edges[edge]
+    }
+
+    pub fn is_blocked(&self, edge: usize, dest: usize) -> bool {
+        self.is_edge_blocked(edge) || self.is_node_blocked(dest)
+    }
+
+    pub fn is_rectangle_disabled(&self, rect: usize) -> bool {
+        !self.disabled_rectangles.is_empty() && self.disabled_rectangles[rect]
+    }
+
+    pub fn is_node_penalized(&self, node: usize) -> bool {
+        !self.node_zones.is_empty() && self.node_zones[node] != 0
+    }
+
+    // Average cost factor of both nodes, so edges leaving a zone are charged half of the extra costs
+    pub fn get_cost_factor(&self, source: usize, dest: usize) -> f64 {
+        if self.node_zones.is_empty() {
+            return 1.0;
+        }
+        let factor = |node: usize| match self.node_zones[node] {
+            0 => 1.0,
+            zone => self.zone_costs[zone as usize - 1].0,
+        };
+        (factor(source) + factor(dest)) / 2.0
+    }
+
+    pub fn get_entry_penalty(&self, source: usize, dest: usize) -> u32 {
+        if self.node_zones.is_empty() || self.node_zones[source] == self.node_zones[dest] {
+            return 0;
+        }
+        match self.node_zones[dest] {
+            0 => 0,
+            zone => self.zone_costs[zone as usize - 1].1,
+        }
+    }
+
+    pub fn get_weight(&self, source: usize, dest: usize, distance: u32) -> u32 {
+        if self.node_zones.is_empty() {
+            return distance;
+        }
+        (distance as f64 * self.get_cost_factor(source, dest)) as u32
+            + self.get_entry_penalty(source, dest)
+    }
+}
+
 pub struct AlgorithmState {
     pub distances: Vec<u32>,
     pub parent_nodes: Vec<u32>,
     pub queue: BinaryHeap<HeapNode>,
     pub astar_queue: BinaryHeap<AStarHeapNode>,
 
+    // Seconds since the departure for the time dependent searches, whose distances contain the costs of
+    // penalty zones. Allocated on first use.
+    pub elapsed_times: Vec<u32>,
+
     // Used for bidirectional algorithms
     pub distances2: Vec<u32>,
     pub parent_nodes2: Vec<u32>,
     pub queue2: BinaryHeap<HeapNode>,
+
+    pub restrictions: Restrictions,
 }
 
 impl AlgorithmState {
     pub fn new(node_count: usize) -> Self {
         AlgorithmState {
-            distances: vec![std::u32::MAX; node_count],
-            parent_nodes: vec![std::u32::MAX; node_count],
+            distances: vec![u32::MAX; node_count],
+            parent_nodes: vec![u32::MAX; node_count],
             queue: BinaryHeap::with_capacity(node_count),
             astar_queue: BinaryHeap::with_capacity(node_count),
 
-            distances2: vec![std::u32::MAX; node_count],
-            parent_nodes2: vec![std::u32::MAX; node_count],
+            elapsed_times: Vec::new(),
+
+            distances2: vec![u32::MAX; node_count],
+            parent_nodes2: vec![u32::MAX; node_count],
             queue2: BinaryHeap::with_capacity(node_count),
+
+            restrictions: Restrictions::default(),
         }
     }
 
     pub fn reset_dijkstra(&mut self) {
         for i in 0..self.distances.len() {
-            self.distances[i] = std::u32::MAX;
-            self.parent_nodes[i] = std::u32::MAX;
+            self.distances[i] = u32::MAX;
+            self.parent_nodes[i] = u32::MAX;
         }
         self.queue.clear();
     }
 
     pub fn reset_bi_dijkstra(&mut self) {
         for i in 0..self.distances.len() {
-            self.distances[i] = std::u32::MAX;
-            self.parent_nodes[i] = std::u32::MAX;
-            self.distances2[i] = std::u32::MAX;
-            self.parent_nodes2[i] = std::u32::MAX;
+            self.distances[i] = u32::MAX;
+            self.parent_nodes[i] = u32::MAX;
+            self.distances2[i] = u32::MAX;
+            self.parent_nodes2[i] = u32::MAX;
         }
         self.queue.clear();
         self.astar_queue.clear();
@@ -165,8 +387,8 @@ impl AlgorithmState {
 
     pub fn reset_astar(&mut self) {
         for i in 0..self.distances.len() {
-            self.distances[i] = std::u32::MAX;
-            self.parent_nodes[i] = std::u32::MAX;
+            self.distances[i] = u32::MAX;
+            self.parent_nodes[i] = u32::MAX;
         }
         self.astar_queue.clear();
     }
@@ -202,6 +424,109 @@ impl Graph {
             && node / self.raster_columns_count < rect.3
     }
 
+    // Nodes of graphs with explicit coordinates are neighbors if they are connected by an edge
+    pub fn is_raster_neighbor(&self, node1: usize, node2: usize) -> bool {
+        if !self.is_raster() {
+            return self.edges[self.offsets[node1].0 as usize..self.offsets[node1 + 1].0 as usize]
+                .iter()
+                .any(|e| e.destination as usize == node2);
+        }
+        let (row1, column1) = (
+            node1 / self.raster_columns_count,
+            node1 % self.raster_columns_count,
+        );
+        let (row2, column2) = (
+            node2 / self.raster_columns_count,
+            node2 % self.raster_columns_count,
+        );
+        let row_offset = row2 as isize - row1 as isize;
+        let mut column_offset = column2 as isize - column1 as isize;
+        // Global rasters wrap around at the antimeridian
+        if self.is_global() && column_offset.abs() > self.raster_columns_count as isize / 2 {
+            column_offset -= column_offset.signum() * self.raster_columns_count as isize;
+        }
+        RASTER_NEIGHBOR_OFFSETS[..self.connectivity as usize].contains(&(row_offset, column_offset))
+    }
+
+    // Shortcut edges represent paths through their rectangle, so they can't be used anymore if any node or
+    // edge inside of the rectangle is restricted. The inner nodes of such rectangles are searched normally.
+    pub fn invalidate_shortcuts(&self, restrictions: &mut Restrictions) {
+        if self.shortcut_rectangles.is_empty() || restrictions.is_empty() {
+            return;
+        }
+
+        restrictions.disabled_rectangles = vec![false; self.shortcut_rectangles.len()];
+        for (i, (left, top, right, bottom)) in self.shortcut_rectangles.iter().enumerate() {
+            let nodes: Vec<usize> = (*top..=*bottom)
+                .flat_map(|row| (*left..=*right).map(move |column| (row, column)))
+                .map(|(row, column)| row * self.raster_columns_count + column)
+                .collect();
+
+            let is_restricted = nodes.iter().any(|node| {
+                restrictions.is_node_blocked(*node)
+                    || restrictions.is_node_penalized(*node)
+                    || (self.offsets[*node].0..self.offsets[*node + 1].0)
+                        .any(|e| restrictions.is_edge_blocked(e as usize))
+            });
+            if !is_restricted {
+                continue;
+            }
+
+            restrictions.disabled_rectangles[i] = true;
+            for node in nodes {
+                for e in self.offsets[node].0 as usize..self.offsets[node + 1].0 as usize {
+                    if !self.is_raster_neighbor(node, self.edges[e].destination as usize) {
+                        restrictions.block_edge(e, self.edges.len());
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn block_shallow_water(&self, min_depth: f64, restrictions: &mut Restrictions) {
+        for (node, depth) in self.depths.iter().enumerate() {
+            if *depth != UNKNOWN_DEPTH && (*depth as f64) < min_depth {
+                restrictions.block_node(node, self.depths.len());
+            }
+        }
+    }
+
+    // Resets the restrictions to the zones, canals, ice, depth limits and corridor of the query
+    pub fn apply_query_options(&self, options: &QueryOptions, restrictions: &mut Restrictions) {
+        *restrictions = Restrictions::default();
+        self.block_zones(&options.exclusion_zones, restrictions);
+        match &self.fuel_model {
+            Some(fuel_model) if options.minimize_fuel_cost => {
+                let mut penalty_zones = options.penalty_zones.clone();
+                penalty_zones.push(fuel_model.get_eca_penalty_zone());
+                self.penalize_zones(&penalty_zones, restrictions);
+            }
+            _ => self.penalize_zones(&options.penalty_zones, restrictions),
+        }
+        self.block_canals(options, restrictions);
+        if self.ice_masks.is_some() {
+            let time = options.departure.unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs() as i64
+            });
+            self.apply_ice(time, options.ice_class, restrictions);
+        }
+        if let Some(draft) = options.draft {
+            self.block_shallow_water(draft + options.under_keel_clearance, restrictions);
+        }
+        if !options.corridor.is_empty() {
+            self.restrict_to_corridor(
+                &options.corridor,
+                options.corridor_width.unwrap_or(DEFAULT_CORRIDOR_WIDTH),
+                restrictions,
+            );
+        }
+        self.invalidate_shortcuts(restrictions);
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn find_path(
         &self,
         lon1: f64,
@@ -209,96 +534,196 @@ impl Graph {
         lon2: f64,
         lat2: f64,
         execution_type: &ExecutionType,
+        options: &QueryOptions,
+        state: &mut AlgorithmState,
+    ) -> Result<Route, String> {
+        self.find_path_with(
+            lon1,
+            lat1,
+            lon2,
+            lat2,
+            options,
+            state,
+            |graph, start, end, start_time, state| match start_time {
+                Some(start_time) => match execution_type {
+                    ExecutionType::AStar | ExecutionType::ShortcutAStar => {
+                        graph.time_dependent_a_star(start, end, start_time, state)
+                    }
+                    _ => graph.time_dependent_dijkstra(start, end, start_time, state),
+                },
+                None => match execution_type {
+                    ExecutionType::Dijkstra => graph.dijkstra(start, end, state),
+                    ExecutionType::BiDijkstra => graph.bi_dijkstra(start, end, state),
+                    ExecutionType::AStar => graph.a_star(start, end, state),
+                    ExecutionType::ShortcutAStar => graph.shortcut_a_star(start, end, state),
+                    ExecutionType::ShortcutDijkstra => graph.shortcut_dijkstra(start, end, state),
+                },
+            },
+        )
+    }
+
+    // Same as find_path, but the path between the start and end node is found by the given search. It gets the
+    // start time if the earliest arrival is searched, the distance of its result is a travel time then.
+    #[allow(clippy::too_many_arguments)]
+    pub fn find_path_with<F>(
+        &self,
+        lon1: f64,
+        lat1: f64,
+        lon2: f64,
+        lat2: f64,
+        options: &QueryOptions,
         state: &mut AlgorithmState,
-    ) -> Option<(GEOJson<Vec<[f64; 2]>>, f64)> {
-        //) -> Option<(GEOJson<[f64; 2]>, f64)> {
+        search: F,
+    ) -> Result<Route, String>
+    where
+        F: FnOnce(&Self, usize, usize, Option<i64>, &mut AlgorithmState) -> PathResult,
+    {
         let mut now = Instant::now();
-        let nearest_start_node = self.find_nearest_node(lon1, lat1);
-        let nearest_end_node = self.find_nearest_node(lon2, lat2);
+        // Nodes blocked by the query aren't snapped to
+        self.apply_query_options(options, &mut state.restrictions);
+        let snap_radius = options.snap_radius.unwrap_or(DEFAULT_SNAP_RADIUS);
+        let snap = |lon: f64, lat: f64, node: Option<usize>| match node {
+            Some(node) => Some((
+                node,
+                Self::calculate_distance(lon, lat, self.get_lon(node), self.get_lat(node)),
+            )),
+            None => self.find_nearest_node_within(lon, lat, snap_radius, &state.restrictions),
+        };
+        let nearest_start_node = snap(lon1, lat1, options.start_node);
+        let nearest_end_node = snap(lon2, lat2, options.end_node);
         println!(
             "Time taken for nearest node search: {}ms",
             now.elapsed().as_micros() as f32 / 1000.
         );
         now = Instant::now();
 
-        let mut coordinates = vec![[lon2, lat2]];
+        let mut coordinates = Vec::new();
+        let mut path_nodes = Vec::new();
         let mut distance = 0;
 
         if nearest_start_node.is_none() || nearest_end_node.is_none() {
             println!("No nearest start or end node found");
-            return None;
+            return Err("No water node found near the start or end position".to_string());
         }
 
-        let nearest_start_node = nearest_start_node.unwrap();
-        let nearest_end_node = nearest_end_node.unwrap();
+        let (nearest_start_node, start_snap_distance) = nearest_start_node.unwrap();
+        let (nearest_end_node, end_snap_distance) = nearest_end_node.unwrap();
+        if !self.are_connected(nearest_start_node, nearest_end_node) {
+            println!("Start and end node are in different water bodies");
+            return Err("Start and end are in different water bodies".to_string());
+        }
 
         println!(
-            "Nearest start node: {},{}",
+            "Nearest start node: {},{} ({}m away)",
             self.get_lon(nearest_start_node),
-            self.get_lat(nearest_start_node)
+            self.get_lat(nearest_start_node),
+            start_snap_distance
         );
         println!(
-            "Nearest end node: {},{}",
+            "Nearest end node: {},{} ({}m away)",
             self.get_lon(nearest_end_node),
-            self.get_lat(nearest_end_node)
+            self.get_lat(nearest_end_node),
+            end_snap_distance
         );
 
+        // Positions from the start to its node and from the end to its node, around the coast if necessary
+        let (nearest_start_node, start_leg) =
+            self.find_coastal_leg(lon1, lat1, nearest_start_node, &state.restrictions);
+        let (nearest_end_node, end_leg) =
+            self.find_coastal_leg(lon2, lat2, nearest_end_node, &state.restrictions);
+        coordinates.extend_from_slice(&end_leg[..end_leg.len() - 1]);
+
+        if state.restrictions.is_node_blocked(nearest_start_node)
+            || state.restrictions.is_node_blocked(nearest_end_node)
+        {
+            println!("Start or end node is blocked by the restrictions of the query");
+            return Err("Start or end is blocked by the restrictions of the query".to_string());
+        }
+
+        // Edge weights become travel times in seconds if the earliest arrival is searched
+        let departure = options.departure.filter(|_| self.weather_model.is_some());
+
         if nearest_start_node == nearest_end_node {
             println!("Start node is equal to end node. Skipping search algorithm");
-            distance += Self::calculate_distance(lon1, lat1, lon2, lat2);
+            if self.crosses_coastline(lon1, lat1, lon2, lat2) {
+                distance += self.calculate_legs_weight(&start_leg, departure);
+                distance += self.calculate_legs_weight(
+                    &end_leg.iter().rev().copied().collect::<Vec<_>>(),
+                    departure.map(|d| d + distance as i64),
+                );
+                coordinates.push([
+                    self.get_lon(nearest_end_node),
+                    self.get_lat(nearest_end_node),
+                ]);
+                coordinates.extend(start_leg[..start_leg.len() - 1].iter().rev());
+            } else {
+                distance += self.calculate_leg_weight(lon1, lat1, lon2, lat2, departure);
+                coordinates = vec![[lon2, lat2], [lon1, lat1]];
+            }
         } else {
             println!("Start node is not equal to end node. Executing search algorithm");
-            let result = match execution_type {
-                ExecutionType::Dijkstra => {
-                    self.dijkstra(nearest_start_node, nearest_end_node, state)
-                }
-                ExecutionType::BiDijkstra => {
-                    self.bi_dijkstra(nearest_start_node, nearest_end_node, state)
-                }
-                ExecutionType::AStar => self.a_star(nearest_start_node, nearest_end_node, state),
-                ExecutionType::ShortcutAStar => {
-                    self.shortcut_a_star(nearest_start_node, nearest_end_node, state)
-                }
-                ExecutionType::ShortcutDijkstra => {
-                    self.shortcut_dijkstra(nearest_start_node, nearest_end_node, state)
-                }
-            };
+            distance += self.calculate_legs_weight(&start_leg, departure);
 
-            if result.path.is_none() || result.distance.is_none() {
-                println!(
-                    "Search algorithm did not find a route and took {}ms",
-                    now.elapsed().as_micros() as f32 / 1000.
-                );
-                return None;
-            } else {
+            let result = search(
+                self,
+                nearest_start_node,
+                nearest_end_node,
+                departure.map(|d| d + distance as i64),
+                state,
+            );
+
+            if let (Some(path), Some(result_distance)) = (result.path, result.distance) {
                 println!(
                     "Search algorithm found a route and took {}ms",
                     now.elapsed().as_micros() as f32 / 1000.
                 );
-                let path = result.path.unwrap();
-                distance += result.distance.unwrap();
+                distance += result_distance;
                 println!("Path length: {}", path.len());
 
                 for node in path.iter() {
                     coordinates.push([self.get_lon(*node), self.get_lat(*node)]);
                 }
 
-                distance += Self::calculate_distance(
-                    lon1,
-                    lat1,
-                    self.get_lon(*path.last().unwrap()),
-                    self.get_lat(*path.last().unwrap()),
+                distance += self.calculate_legs_weight(
+                    &end_leg.iter().rev().copied().collect::<Vec<_>>(),
+                    departure.map(|d| d + distance as i64),
                 );
-                distance += Self::calculate_distance(
-                    self.get_lon(path[0]),
-                    self.get_lat(path[0]),
-                    lon2,
-                    lat2,
+                coordinates.extend(start_leg[..start_leg.len() - 1].iter().rev());
+                path_nodes = path;
+            } else {
+                println!(
+                    "Search algorithm did not find a route and took {}ms",
+                    now.elapsed().as_micros() as f32 / 1000.
                 );
+                return Err("No path could be found".to_string());
             }
         }
 
-        coordinates.push([lon1, lat1]);
+        let canal_transits = self.get_canal_transits(&path_nodes);
+
+        // Edge weights contain the costs of penalty zones, ice and ECAs, which only guide the search, so the
+        // weights of the edges are summed up without them
+        if departure.is_none()
+            && !state.restrictions.node_zones.is_empty()
+            && !path_nodes.is_empty()
+        {
+            distance = self.calculate_legs_weight(&start_leg, None)
+                + self.calculate_path_distance(&path_nodes)
+                + self.calculate_legs_weight(
+                    &end_leg.iter().rev().copied().collect::<Vec<_>>(),
+                    None,
+                );
+        }
+
+        let mut travel_time = None;
+        if departure.is_some() {
+            travel_time = Some(distance as f64);
+            distance = self.calculate_route_distance(&coordinates, &canal_transits);
+        } else if let Some(cost_model) = &self.cost_model {
+            // Edge weights are travel times, so the sailed distance has to be summed up along the route
+            travel_time = Some(distance as f64 / cost_model.reference_speed);
+            distance = self.calculate_route_distance(&coordinates, &canal_transits);
+        }
 
         let mut geojson = GEOJson {
             r#type: "FeatureCollection",
@@ -374,71 +799,301 @@ impl Graph {
             });
         } */
 
-        Some((geojson, distance as f64))
+        let zone_distances = zones::measure_zone_distances(&coordinates, &options.penalty_zones)
+            .into_iter()
+            .enumerate()
+            .map(|(i, d)| ZoneDistance {
+                name: options.penalty_zones[i]
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| format!("Zone {}", i + 1)),
+                distance: d / METERS_PER_NAUTICAL_MILE,
+            })
+            .collect();
+
+        let emissions = self
+            .fuel_model
+            .as_ref()
+            .map(|m| m.estimate(&coordinates, distance as f64, travel_time));
+        let canal_transits = canal_transits.iter().map(|c| c.get_transit()).collect();
+
+        coordinates.reverse();
+        let sea_areas = self.measure_sea_areas(&coordinates);
+        Ok(Route {
+            geojson,
+            waypoints: coordinates,
+            distance: distance as f64,
+            travel_time,
+            zone_distances,
+            canal_transits,
+            emissions,
+            sea_areas,
+            start_snap_distance: start_snap_distance as f64,
+            end_snap_distance: end_snap_distance as f64,
+        })
+    }
+
+    // Sailed distance along the coordinates, with the straight lines of canal transits replaced by
+    // the length of the canals
+    fn calculate_route_distance(&self, coordinates: &[[f64; 2]], canal_transits: &[&Canal]) -> u32 {
+        let mut distance: u32 = coordinates
+            .windows(2)
+            .map(|c| Self::calculate_distance(c[0][0], c[0][1], c[1][0], c[1][1]))
+            .sum();
+        for canal in canal_transits.iter() {
+            let (entry, exit) = canal.nodes;
+            distance -= Self::calculate_distance(
+                self.get_lon(entry),
+                self.get_lat(entry),
+                self.get_lon(exit),
+                self.get_lat(exit),
+            );
+            distance += (canal.distance * METERS_PER_NAUTICAL_MILE) as u32;
+        }
+        distance
+    }
+
+    // Sum of the edge distances along the path, which is stored from end to start
+    fn calculate_path_distance(&self, path: &[usize]) -> u32 {
+        path.windows(2)
+            .map(|nodes| {
+                (self.offsets[nodes[1]].0..self.offsets[nodes[1] + 1].0)
+                    .map(|e| self.edges[e as usize])
+                    .filter(|e| e.destination as usize == nodes[0])
+                    .map(|e| e.distance)
+                    .min()
+                    .unwrap()
+            })
+            .sum()
+    }
+
+    // Weight of the connections between consecutive positions, in seconds if a time is given
+    fn calculate_legs_weight(&self, positions: &[[f64; 2]], time: Option<i64>) -> u32 {
+        let mut weight = 0;
+        for leg in positions.windows(2) {
+            weight += self.calculate_leg_weight(
+                leg[0][0],
+                leg[0][1],
+                leg[1][0],
+                leg[1][1],
+                time.map(|t| t + weight as i64),
+            );
+        }
+        weight
+    }
+
+    // Weight of the straight connection between a position and its nearest node, in seconds if a time is given
+    fn calculate_leg_weight(
+        &self,
+        lon1: f64,
+        lat1: f64,
+        lon2: f64,
+        lat2: f64,
+        time: Option<i64>,
+    ) -> u32 {
+        let distance = Self::calculate_distance(lon1, lat1, lon2, lat2);
+        if let (Some(time), Some(weather_model)) = (time, &self.weather_model) {
+            return weather_model
+                .travel_time(lon1, lat1, lon2, lat2, time)
+                .unwrap_or(distance as f64 / weather_model.vessel_speed)
+                .ceil() as u32;
+        }
+        match &self.cost_model {
+            Some(cost_model) => cost_model.edge_weight(lon1, lat1, lon2, lat2).unwrap_or(
+                (distance as f64 * cost_model.reference_speed / cost_model.vessel_speed) as u32,
+            ),
+            None => distance,
+        }
     }
 
     pub fn find_nearest_node(&self, lon: f64, lat: f64) -> Option<usize> {
-        let step_size_lon = (360_0000000.0 / self.raster_columns_count as f64) as usize;
-        let lon_index_left = ((lon + 180.) * FACTOR) as usize / step_size_lon;
-        let lon_index_right = (lon_index_left + 1) % self.raster_columns_count;
-
-        let step_size_lat = (180_0000000.0 / self.raster_rows_count as f64) as usize;
-        let lat_index_top = ((-lat + 90.) * FACTOR) as usize / step_size_lat;
-        let lat_index_bottom = (lat_index_top + 1) % self.raster_rows_count;
-
-        let neighbor_ids = vec![
-            (lat_index_top * self.raster_columns_count) + lon_index_left,
-            (lat_index_top * self.raster_columns_count) + lon_index_right,
-            (lat_index_bottom * self.raster_columns_count) + lon_index_left,
-            (lat_index_bottom * self.raster_columns_count) + lon_index_right,
-        ];
-
-        let mut best_neighbor = neighbor_ids[0];
-        let mut min_distance = u32::MAX;
-        for neighbor in neighbor_ids {
-            let distance =
-                Self::calculate_distance(lon, lat, self.get_lon(neighbor), self.get_lat(neighbor));
-
-            let is_neigbor_water = self.offsets[neighbor] != self.offsets[neighbor + 1];
-
-            if is_neigbor_water && distance < min_distance {
-                best_neighbor = neighbor;
-                min_distance = distance;
-            }
+        self.find_nearest_node_within(lon, lat, DEFAULT_SNAP_RADIUS, &Restrictions::default())
+            .map(|(node, _)| node)
+    }
+
+    // Nearest water node within the radius (in meters) and its distance. Nodes of the largest connected
+    // component are preferred, so positions in small lakes or enclosed basins snap to the open sea if possible
+    pub fn find_nearest_node_within(
+        &self,
+        lon: f64,
+        lat: f64,
+        radius: f64,
+        restrictions: &Restrictions,
+    ) -> Option<(usize, u32)> {
+        if !self.is_raster() {
+            return self.find_nearest_indexed_node_within(lon, lat, radius, restrictions);
         }
+        let is_global = self.is_global();
+        let lon = if is_global {
+            (lon + 180.0).rem_euclid(360.0) - 180.0
+        } else {
+            lon
+        };
+        let lat = lat.clamp(-90.0, 90.0);
+
+        // Positions outside of a regional raster start at its border
+        let lat_step = self.get_lat_step();
+        let lon_step = self.get_lon_step();
+        let column = (((lon - self.bounding_box.0) / lon_step).floor().max(0.0) as usize)
+            .min(self.raster_columns_count - 1);
+        let row = (((self.bounding_box.3 - lat) / lat_step).floor().max(0.0) as usize)
+            .min(self.raster_rows_count - 1);
+
+        // Size of the search window in raster steps, the columns get wider towards the poles
+        let lat_step_meters = Self::calculate_distance(0.0, 0.0, 0.0, lat_step) as f64;
+        let lon_step_meters = Self::calculate_distance(0.0, 0.0, lon_step, 0.0) as f64;
+        let rows_radius = (radius / lat_step_meters).ceil() as usize + 1;
+        let max_lat = (lat.abs() + (rows_radius as f64) * lat_step).min(90.0);
+        let min_step_meters = lat_step_meters.min(lon_step_meters * max_lat.to_radians().cos());
+        let max_columns_radius = if is_global {
+            self.raster_columns_count / 2
+        } else {
+            self.raster_columns_count
+        };
+        let columns_radius =
+            ((radius / min_step_meters).ceil() + 1.0).min(max_columns_radius as f64) as usize;
+
+        let mut best_node = None;
+        let mut best_largest_component_node = None;
+        for ring in 0..=rows_radius.max(columns_radius) {
+            let ring_rows = ring.min(rows_radius) as isize;
+            let ring_columns = ring.min(columns_radius) as isize;
+            for dr in -ring_rows..=ring_rows {
+                let r = row as isize + dr;
+                if r < 0 || r >= self.raster_rows_count as isize {
+                    continue;
+                }
+                for dc in -ring_columns..=ring_columns {
+                    // Only the border of the ring, the inside was checked before
+                    if dr.unsigned_abs() != ring && dc.unsigned_abs() != ring {
+                        continue;
+                    }
+                    let mut c = column as isize + dc;
+                    if is_global {
+                        c = c.rem_euclid(self.raster_columns_count as isize);
+                    } else if c < 0 || c >= self.raster_columns_count as isize {
+                        continue;
+                    }
+                    let node = r as usize * self.raster_columns_count + c as usize;
+                    if self.offsets[node] == self.offsets[node + 1]
+                        || restrictions.is_node_blocked(node)
+                    {
+                        continue;
+                    }
+
+                    let distance =
+                        Self::calculate_distance(lon, lat, self.get_lon(node), self.get_lat(node));
+                    if distance as f64 > radius {
+                        continue;
+                    }
+                    if best_node.is_none_or(|(_, d)| distance < d) {
+                        best_node = Some((node, distance));
+                    }
+                    if self.is_in_largest_component(node)
+                        && best_largest_component_node.is_none_or(|(_, d)| distance < d)
+                    {
+                        best_largest_component_node = Some((node, distance));
+                    }
+                }
+            }
 
-        if min_distance == u32::MAX {
-            return None;
+            // Nodes of the following rings are at least this far away
+            if let Some((_, distance)) = best_largest_component_node {
+                if distance as f64 <= ring as f64 * min_step_meters {
+                    break;
+                }
+            }
         }
-        Some(best_neighbor)
+
+        best_largest_component_node.or(best_node)
     }
 
     pub fn new_from_binfile(filename: &str) -> Self {
         println!("Creating Graph from binary file: {}", filename);
-        let mut buf_reader = BufReader::new(File::open(&filename).unwrap());
-        let graph: Self = bincode::deserialize_from(&mut buf_reader).unwrap();
+        let mut buf_reader = BufReader::new(File::open(filename).unwrap());
+        let header: ([u8; 4], u32) = bincode::deserialize_from(&mut buf_reader).unwrap();
+        if header != (GRAPH_FILE_MAGIC, GRAPH_FORMAT_VERSION) {
+            panic!(
+                "{} wasn't created with graph format version {}, please extract the graph again",
+                filename, GRAPH_FORMAT_VERSION
+            );
+        }
+        let mut graph: Self = bincode::deserialize_from(&mut buf_reader).unwrap();
+        if graph.components.is_empty() {
+            graph.label_components();
+        }
+        graph.build_spatial_index();
         println!("Created Graph");
         graph
     }
 
     pub fn write_to_binfile(&self, filename: &str) {
         println!("Saving Graph to binary file: {}", filename);
-        let mut buf_writer = BufWriter::new(File::create(&filename).unwrap());
+        let mut buf_writer = BufWriter::new(File::create(filename).unwrap());
+        bincode::serialize_into(&mut buf_writer, &(GRAPH_FILE_MAGIC, GRAPH_FORMAT_VERSION))
+            .unwrap();
         bincode::serialize_into(&mut buf_writer, &self).unwrap();
     }
 
     pub fn get_lon(&self, i: usize) -> f64 {
-        let step_size = (360_0000000.0 / self.raster_columns_count as f64) as usize;
+        if !self.is_raster() {
+            return self.coordinates[i][0];
+        }
+        let step_size = ((self.bounding_box.2 - self.bounding_box.0) * FACTOR
+            / self.raster_columns_count as f64) as usize;
         let coordinate = (i % self.raster_columns_count) * step_size;
         let coordinate = coordinate as f64 / FACTOR;
-        coordinate - 180.0
+        self.bounding_box.0 + coordinate
     }
 
     pub fn get_lat(&self, i: usize) -> f64 {
-        let step_size = (180_0000000.0 / self.raster_rows_count as f64) as usize;
+        if !self.is_raster() {
+            return self.coordinates[i][1];
+        }
+        let step_size = ((self.bounding_box.3 - self.bounding_box.1) * FACTOR
+            / self.raster_rows_count as f64) as usize;
         let coordinate = (i / self.raster_columns_count) * step_size;
         let coordinate = coordinate as f64 / FACTOR;
-        90.0 - coordinate
+        self.bounding_box.3 - coordinate
+    }
+
+    // Graphs with explicit coordinates use the mean distance between their nodes in degrees at the equator
+    pub fn get_lon_step(&self) -> f64 {
+        if !self.is_raster() {
+            return self.get_mean_node_spacing();
+        }
+        (self.bounding_box.2 - self.bounding_box.0) / self.raster_columns_count as f64
+    }
+
+    pub fn get_lat_step(&self) -> f64 {
+        if !self.is_raster() {
+            return self.get_mean_node_spacing();
+        }
+        (self.bounding_box.3 - self.bounding_box.1) / self.raster_rows_count as f64
+    }
+
+    fn get_mean_node_spacing(&self) -> f64 {
+        let (min_lon, min_lat, max_lon, max_lat) = self.bounding_box;
+        let area = (max_lon - min_lon).to_radians()
+            * (max_lat.to_radians().sin() - min_lat.to_radians().sin());
+        (area / self.coordinates.len() as f64).sqrt().to_degrees()
+    }
+
+    pub fn is_raster(&self) -> bool {
+        self.coordinates.is_empty()
+    }
+
+    pub fn node_count(&self) -> usize {
+        if self.is_raster() {
+            self.raster_columns_count * self.raster_rows_count
+        } else {
+            self.coordinates.len()
+        }
+    }
+
+    // Columns of global rasters wrap around at the antimeridian
+    pub fn is_global(&self) -> bool {
+        self.bounding_box.2 - self.bounding_box.0 >= 360.0
     }
 
     pub fn calculate_distance(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> u32 {
@@ -475,9 +1130,20 @@ impl Graph {
             )) as u32
     }
 
+    // Lower bound of the distance for the A* algorithms. Diagonal edges and edges of graphs with explicit
+    // coordinates don't follow the meridians and parallels, so only the great circle distance is admissible.
+    pub fn heuristic_distance(&self, lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> u32 {
+        if self.is_raster() && self.connectivity == 4 {
+            Self::manhattan_distance(lon1, lat1, lon2, lat2)
+        } else {
+            // Edge distances are truncated to meters, which adds up along long routes of short edges
+            (Self::calculate_distance(lon1, lat1, lon2, lat2) as f64 * 0.999) as u32
+        }
+    }
+
     pub fn generate_random_water_nodes(&self, amount: usize) -> Vec<(usize, usize)> {
         let mut water_nodes = Vec::new();
-        for i in 0..(self.raster_rows_count * self.raster_columns_count) {
+        for i in 0..self.node_count() {
             if self.get_lat(i) < -83.0 || self.get_lat(i) > 85.01 {
                 // Ignore points outside of WGS84
                 continue;
@@ -505,6 +1171,13 @@ impl Graph {
 
     pub fn dijkstra(&self, start: usize, end: usize, state: &mut AlgorithmState) -> PathResult {
         state.reset_dijkstra();
+        if !self.are_connected(start, end) {
+            return PathResult {
+                path: None,
+                distance: None,
+                heap_pops: 0,
+            };
+        }
 
         state.distances[start] = 0;
         state.queue.push(HeapNode {
@@ -535,7 +1208,14 @@ impl Graph {
                 ..self.offsets[node.id as usize + 1].0 as usize
             {
                 let dest = self.edges[i].destination;
-                let dist = self.edges[i].distance;
+                if state.restrictions.is_blocked(i, dest as usize) {
+                    continue;
+                }
+                let dist = state.restrictions.get_weight(
+                    node.id as usize,
+                    dest as usize,
+                    self.edges[i].distance,
+                );
                 let new_distance = state.distances[node.id as usize] + dist;
 
                 if new_distance < state.distances[dest as usize] {
@@ -559,7 +1239,14 @@ impl Graph {
 
     pub fn bi_dijkstra(&self, start: usize, end: usize, state: &mut AlgorithmState) -> PathResult {
         state.reset_bi_dijkstra();
-        let mut shortest_distance = std::u32::MAX;
+        if !self.are_connected(start, end) {
+            return PathResult {
+                path: None,
+                distance: None,
+                heap_pops: 0,
+            };
+        }
+        let mut shortest_distance = u32::MAX;
         let mut middle_node = 0;
 
         state.distances[start] = 0;
@@ -613,7 +1300,14 @@ impl Graph {
                 ..self.offsets[node.id as usize + 1].0 as usize
             {
                 let dest = self.edges[i].destination;
-                let dist = self.edges[i].distance;
+                if state.restrictions.is_blocked(i, dest as usize) {
+                    continue;
+                }
+                let dist = state.restrictions.get_weight(
+                    node.id as usize,
+                    dest as usize,
+                    self.edges[i].distance,
+                );
                 let new_distance = state.distances[node.id as usize] + dist;
 
                 if new_distance < state.distances[dest as usize] {
@@ -624,7 +1318,7 @@ impl Graph {
                     state.distances[dest as usize] = new_distance;
                     state.parent_nodes[dest as usize] = node.id;
 
-                    if state.distances2[dest as usize] != std::u32::MAX {
+                    if state.distances2[dest as usize] != u32::MAX {
                         let d = state.distances[node.id as usize]
                             + dist
                             + state.distances2[dest as usize];
@@ -640,7 +1334,17 @@ impl Graph {
                 ..self.offsets[node2.id as usize + 1].0 as usize
             {
                 let dest = self.edges[i].destination;
-                let dist = self.edges[i].distance;
+                if state.restrictions.is_blocked(i, dest as usize) {
+                    continue;
+                }
+                let dist = match self.get_reverse_distance(i, node2.id as usize) {
+                    Some(dist) => {
+                        state
+                            .restrictions
+                            .get_weight(dest as usize, node2.id as usize, dist)
+                    }
+                    None => continue,
+                };
                 let new_distance = state.distances2[node2.id as usize] + dist;
 
                 if new_distance < state.distances2[dest as usize] {
@@ -651,7 +1355,7 @@ impl Graph {
                     state.distances2[dest as usize] = new_distance;
                     state.parent_nodes2[dest as usize] = node2.id;
 
-                    if state.distances[dest as usize] != std::u32::MAX {
+                    if state.distances[dest as usize] != u32::MAX {
                         let d = state.distances[dest as usize]
                             + dist
                             + state.distances2[node2.id as usize];
@@ -676,6 +1380,13 @@ impl Graph {
         let end_lon = self.get_lon(end);
         let end_lat = self.get_lat(end);
         state.reset_astar();
+        if !self.are_connected(start, end) {
+            return PathResult {
+                path: None,
+                distance: None,
+                heap_pops: 0,
+            };
+        }
 
         state.distances[start] = 0;
         state.astar_queue.push(AStarHeapNode {
@@ -712,7 +1423,13 @@ impl Graph {
                 ..self.offsets[node.id as usize + 1].0 as usize
             {
                 let dest = self.edges[i].destination as usize;
-                let dist = self.edges[i].distance;
+                if state.restrictions.is_blocked(i, dest) {
+                    continue;
+                }
+                let dist =
+                    state
+                        .restrictions
+                        .get_weight(node.id as usize, dest, self.edges[i].distance);
                 let g_value = node.g_value + dist;
 
                 if g_value < state.distances[dest] {
@@ -723,7 +1440,7 @@ impl Graph {
                         id: dest as u32,
                         g_value,
                         f_value: g_value
-                            + Self::manhattan_distance(
+                            + self.heuristic_distance(
                                 self.get_lon(dest),
                                 self.get_lat(dest),
                                 end_lon,
@@ -751,6 +1468,13 @@ impl Graph {
         let end_lon = self.get_lon(end);
         let end_lat = self.get_lat(end);
         state.reset_astar();
+        if !self.are_connected(start, end) {
+            return PathResult {
+                path: None,
+                distance: None,
+                heap_pops: 0,
+            };
+        }
 
         state.distances[start] = 0;
         state.astar_queue.push(AStarHeapNode {
@@ -799,13 +1523,24 @@ impl Graph {
                 ..self.offsets[node.id as usize + 1].0 as usize
             {
                 let dest = self.edges[i].destination as usize;
-                let dist = self.edges[i].distance;
+                if state.restrictions.is_blocked(i, dest) {
+                    continue;
+                }
+                let dist =
+                    state
+                        .restrictions
+                        .get_weight(node.id as usize, dest, self.edges[i].distance);
                 let g_value = node.g_value + dist;
 
                 if g_value < state.distances[dest] {
-                    // Skip neighbor if it is inside a shortcut rectangle and the start/end node are not inside the rectangle
+                    // Skip neighbor if it is inside a shortcut rectangle and the start/end node are not inside the rectangle,
+                    // unless the shortcuts of the rectangle can't be used due to restrictions
                     let rect = self.offsets[dest].1;
-                    if rect.is_some() && rect.unwrap() != start_rect && rect.unwrap() != end_rect {
+                    if rect.is_some()
+                        && rect.unwrap() != start_rect
+                        && rect.unwrap() != end_rect
+                        && !state.restrictions.is_rectangle_disabled(rect.unwrap())
+                    {
                         continue;
                     }
 
@@ -816,7 +1551,7 @@ impl Graph {
                         id: dest as u32,
                         g_value,
                         f_value: g_value
-                            + Self::manhattan_distance(
+                            + self.heuristic_distance(
                                 self.get_lon(dest),
                                 self.get_lat(dest),
                                 end_lon,
@@ -842,6 +1577,13 @@ impl Graph {
         state: &mut AlgorithmState,
     ) -> PathResult {
         state.reset_dijkstra();
+        if !self.are_connected(start, end) {
+            return PathResult {
+                path: None,
+                distance: None,
+                heap_pops: 0,
+            };
+        }
 
         state.distances[start] = 0;
         state.queue.push(HeapNode {
@@ -884,13 +1626,25 @@ impl Graph {
                 ..self.offsets[node.id as usize + 1].0 as usize
             {
                 let dest = self.edges[i].destination;
-                let dist = self.edges[i].distance;
+                if state.restrictions.is_blocked(i, dest as usize) {
+                    continue;
+                }
+                let dist = state.restrictions.get_weight(
+                    node.id as usize,
+                    dest as usize,
+                    self.edges[i].distance,
+                );
                 let new_distance = state.distances[node.id as usize] + dist;
 
                 if new_distance < state.distances[dest as usize] {
-                    // Skip neighbor if it is inside a shortcut rectangle and the start/end node are not inside the rectangle
+                    // Skip neighbor if it is inside a shortcut rectangle and the start/end node are not inside the rectangle,
+                    // unless the shortcuts of the rectangle can't be used due to restrictions
                     let rect = self.offsets[dest as usize].1;
-                    if rect.is_some() && rect.unwrap() != start_rect && rect.unwrap() != end_rect {
+                    if rect.is_some()
+                        && rect.unwrap() != start_rect
+                        && rect.unwrap() != end_rect
+                        && !state.restrictions.is_rectangle_disabled(rect.unwrap())
+                    {
                         continue;
                     }
 
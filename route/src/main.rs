@@ -1,14 +1,46 @@
 use rouille::Response;
 use std::{env, str::FromStr};
 
-mod lib;
-use lib::{AlgorithmState, ExecutionType, GEOJson, Graph};
+use route::canals::{Canal, CanalTransit};
+use route::coastlines::CoastlineIndex;
+use route::emissions::{Emissions, FuelCurve, FuelModel};
+use route::environment::{self, CostModel, VectorField, VectorFieldSeries, WeatherModel};
+use route::export::{self, ExportFormat};
+use route::ice::{IceClass, IceMasks};
+use route::ports::{Port, PortRegistry};
+use route::sea_areas::SeaAreaDistance;
+use route::zones::Zone;
+use route::{
+    AlgorithmState, ExecutionType, GEOJson, Graph, QueryOptions, Route, ZoneDistance,
+    METERS_PER_NAUTICAL_MILE,
+};
+
+const DEFAULT_VESSEL_SPEED: f64 = 12.0; // knots
+const DEFAULT_WIND_FACTOR: f64 = 0.025;
+const DEFAULT_HFO_PRICE: f64 = 500.0; // Per tonne
+const DEFAULT_MGO_PRICE: f64 = 750.0; // Per tonne
+const MAX_PORT_SUGGESTIONS: usize = 10;
+const DEFAULT_NEAREST_PORTS: usize = 5;
 
 #[derive(serde::Serialize)]
 struct RouteResponse {
     geojson: GEOJson<Vec<[f64; 2]>>,
     //geojson: GEOJson<[f64; 2]>,
     distance: f64,
+    travel_time: Option<f64>,
+    zone_distances: Vec<ZoneDistance>,
+    canal_transits: Vec<CanalTransit>,
+    emissions: Option<Emissions>,
+    sea_areas: Vec<SeaAreaDistance>,
+    start_snap_distance: f64,
+    end_snap_distance: f64,
+}
+
+#[derive(serde::Serialize)]
+struct NearestPortResponse<'a> {
+    port: &'a Port,
+    route: Option<RouteResponse>,
+    error: Option<String>, // Reason why no route to the port could be created
 }
 
 /* #[derive(serde::Serialize, serde::Deserialize)]
@@ -56,6 +88,23 @@ fn main() {
         for s in ExecutionType::get_strings() {
             println!("  - {}", s);
         }
+        println!("Options:");
+        println!("  --currents <csv file>   Current field (lon,lat,u,v in m/s)");
+        println!("  --wind <csv file>       Wind field (lon,lat,u,v in m/s)");
+        println!("  --currents-series <csv file>  Current forecast (time,lon,lat,u,v in m/s)");
+        println!("  --wind-series <csv file>      Wind forecast (time,lon,lat,u,v in m/s)");
+        println!("  --speed <knots>         Vessel speed through water");
+        println!("  --wind-factor <factor>  Fraction of the wind speed causing drift");
+        println!("  --exclusion-zones <geojson file>  Polygons which are never entered");
+        println!("  --penalty-zones <geojson file>    Polygons with cost_factor and entry_penalty (nm) properties");
+        println!("  --canals <json file>    Canals and straits added as extra edges");
+        println!("  --ice-masks <list file> Monthly or dated sea ice concentration rasters (month or date,asc file[,percent])");
+        println!("  --fuel-curve <csv file> Fuel consumption (speed in knots,tonnes per day)");
+        println!("  --eca-zones <geojson file>  Emission Control Areas");
+        println!("  --fuel-prices <hfo price>,<mgo price>  Fuel prices per tonne");
+        println!("  --coastlines <geojson file>  Coastline polygons written by extract, for land safe first and last miles");
+        println!("  --ports <csv file>      Ports (name,locode,lon,lat,max_draft) for routing by UN/LOCODE");
+        println!("  --coarse-graph <graph file>  Coarse graph whose routes restrict the search to a corridor");
         return;
     }
 
@@ -71,7 +120,103 @@ fn main() {
     let marker_icon = include_bytes!("marker-icon.png");
     let marker_icon2 = include_bytes!("marker-icon2.png");
 
-    let graph = Graph::new_from_binfile(&args[1]);
+    let mut currents = None;
+    let mut wind = None;
+    let mut currents_series = None;
+    let mut wind_series = None;
+    let mut vessel_speed = DEFAULT_VESSEL_SPEED;
+    let mut wind_factor = DEFAULT_WIND_FACTOR;
+    let mut exclusion_zones = Vec::new();
+    let mut penalty_zones = Vec::new();
+    let mut canals = Vec::new();
+    let mut ice_masks = None;
+    let mut fuel_curve = None;
+    let mut eca_zones = Vec::new();
+    let mut fuel_prices = (DEFAULT_HFO_PRICE, DEFAULT_MGO_PRICE);
+    let mut coastlines = Vec::new();
+    let mut ports_file = None;
+    let mut coarse_graph_file = None;
+    for option in args[3..].chunks(2) {
+        if option.len() < 2 {
+            println!("Missing value for option {}", option[0]);
+            return;
+        }
+        match option[0].as_str() {
+            "--currents" => currents = Some(VectorField::new_from_csvfile(&option[1])),
+            "--wind" => wind = Some(VectorField::new_from_csvfile(&option[1])),
+            "--currents-series" => {
+                currents_series = Some(VectorFieldSeries::new_from_csvfile(&option[1]))
+            }
+            "--wind-series" => wind_series = Some(VectorFieldSeries::new_from_csvfile(&option[1])),
+            "--speed" => vessel_speed = parse_option(&option[0], &option[1]),
+            "--wind-factor" => wind_factor = parse_option(&option[0], &option[1]),
+            "--exclusion-zones" => exclusion_zones = Zone::new_from_geojson_file(&option[1]),
+            "--penalty-zones" => penalty_zones = Zone::new_from_geojson_file(&option[1]),
+            "--canals" => canals = Canal::new_from_jsonfile(&option[1]),
+            "--ice-masks" => ice_masks = Some(IceMasks::new_from_listfile(&option[1])),
+            "--fuel-curve" => fuel_curve = Some(FuelCurve::new_from_csvfile(&option[1])),
+            "--eca-zones" => eca_zones = Zone::new_from_geojson_file(&option[1]),
+            "--coastlines" => coastlines = Zone::new_from_geojson_file(&option[1]),
+            "--ports" => ports_file = Some(option[1].clone()),
+            "--coarse-graph" => coarse_graph_file = Some(option[1].clone()),
+            "--fuel-prices" => {
+                let (hfo_price, mgo_price) = option[1].split_once(',').unwrap_or_else(|| {
+                    panic!(
+                        "Invalid value {} for --fuel-prices, expected <hfo price>,<mgo price>",
+                        option[1]
+                    )
+                });
+                fuel_prices = (
+                    parse_option(&option[0], hfo_price),
+                    parse_option(&option[0], mgo_price),
+                );
+            }
+            _ => {
+                println!("Unknown option {}", option[0]);
+                return;
+            }
+        }
+    }
+
+    let mut graph = Graph::new_from_binfile(&args[1]);
+    if currents.is_some() || wind.is_some() {
+        graph.apply_cost_model(CostModel::new(vessel_speed, currents, wind, wind_factor));
+    }
+    if currents_series.is_some() || wind_series.is_some() {
+        graph.set_weather_model(WeatherModel::new(
+            vessel_speed,
+            currents_series,
+            wind_series,
+            wind_factor,
+        ));
+    }
+    // The coarse graph only needs the canals, the costs of the route are computed on the fine graph
+    let coarse_graph = coarse_graph_file.map(|file| {
+        let mut coarse_graph = Graph::new_from_binfile(&file);
+        if !canals.is_empty() {
+            coarse_graph.add_canals(canals.clone());
+        }
+        coarse_graph
+    });
+    if !canals.is_empty() {
+        graph.add_canals(canals);
+    }
+    graph.ice_masks = ice_masks;
+    graph.coastlines = CoastlineIndex::new(&coastlines);
+    if let Some(fuel_curve) = fuel_curve {
+        graph.fuel_model = Some(FuelModel::new(
+            fuel_curve,
+            vessel_speed,
+            eca_zones,
+            fuel_prices.0,
+            fuel_prices.1,
+        ));
+    }
+
+    let port_registry = match ports_file {
+        Some(ports_file) => PortRegistry::new_from_csvfile(&ports_file, &graph),
+        None => PortRegistry { ports: Vec::new() },
+    };
 
     rouille::start_server("localhost:8000", move |request| {
         rouille::router!(request,
@@ -80,24 +225,171 @@ fn main() {
             },
 
             (POST) (/) => {
+                let format = match get_format(request) {
+                    Ok(format) => format,
+                    Err(response) => return response,
+                };
                 let input = rouille::try_or_400!(rouille::post_input!(request, {
                     lat1: f64,
                     lon1: f64,
                     lat2: f64,
                     lon2: f64,
+                    departure: Option<String>,
+                    exclusion_zones: Option<String>,
+                    penalty_zones: Option<String>,
+                    draft: Option<f64>,
+                    under_keel_clearance: Option<f64>,
+                    beam: Option<f64>,
+                    air_draft: Option<f64>,
+                    forbidden_canals: Option<String>,
+                    ice_class: Option<String>,
+                    minimize_fuel_cost: bool,
+                    snap_radius: Option<f64>,
+                    corridor_width: Option<f64>,
                 }));
 
                 println!("Marker 1 at: {},{}", input.lon1, input.lat1);
                 println!("Marker 2 at: {},{}", input.lon2, input.lat2);
 
-                let mut state = AlgorithmState::new(graph.raster_columns_count * graph.raster_rows_count);
-                let result = graph.find_path(input.lon1, input.lat1, input.lon2, input.lat2, &execution_type, &mut state);
+                let mut options = QueryOptions {
+                    exclusion_zones: exclusion_zones.clone(),
+                    penalty_zones: penalty_zones.clone(),
+                    draft: input.draft,
+                    under_keel_clearance: input.under_keel_clearance.unwrap_or(0.0),
+                    minimize_fuel_cost: input.minimize_fuel_cost,
+                    beam: input.beam,
+                    air_draft: input.air_draft,
+                    snap_radius: input.snap_radius.map(|r| r * METERS_PER_NAUTICAL_MILE),
+                    corridor_width: input.corridor_width.map(|w| w * METERS_PER_NAUTICAL_MILE),
+                    forbidden_canals: input
+                        .forbidden_canals
+                        .map(|c| c.split(',').map(|n| n.trim().to_string()).filter(|n| !n.is_empty()).collect())
+                        .unwrap_or_default(),
+                    ..Default::default()
+                };
+                if let Some(departure) = input.departure.filter(|d| !d.is_empty()) {
+                    match environment::parse_timestamp(&departure) {
+                        Some(timestamp) => options.departure = Some(timestamp),
+                        None => return Response::text("Invalid departure time").with_status_code(400),
+                    }
+                }
+                if let Some(ice_class) = input.ice_class {
+                    match IceClass::from_str(&ice_class) {
+                        Ok(ice_class) => options.ice_class = ice_class,
+                        Err(()) => return Response::text(format!("Invalid ice class, possible ice classes: {}", IceClass::get_strings().join(", "))).with_status_code(400),
+                    }
+                }
+                if let Some(geojson) = input.exclusion_zones.filter(|z| !z.is_empty()) {
+                    match Zone::new_from_geojson(&geojson) {
+                        Ok(zones) => options.exclusion_zones.extend(zones),
+                        Err(e) => return Response::text(format!("Invalid exclusion zones: {}", e)).with_status_code(400),
+                    }
+                }
+                if let Some(geojson) = input.penalty_zones.filter(|z| !z.is_empty()) {
+                    match Zone::new_from_geojson(&geojson) {
+                        Ok(zones) => options.penalty_zones.extend(zones),
+                        Err(e) => return Response::text(format!("Invalid penalty zones: {}", e)).with_status_code(400),
+                    }
+                }
+
+                let mut state = AlgorithmState::new(graph.node_count());
+                let result = match &coarse_graph {
+                    Some(coarse_graph) => graph.find_path_in_corridor(coarse_graph, input.lon1, input.lat1, input.lon2, input.lat2, &execution_type, &options, &mut state),
+                    None => graph.find_path(input.lon1, input.lat1, input.lon2, input.lat2, &execution_type, &options, &mut state),
+                };
                 println!("Done!\n");
-                if let Some((geojson, distance)) = result {
-                    let route_response = RouteResponse {geojson, distance};
-                    return Response::json(&route_response);
+                create_response(result, format, "Route")
+            },
+
+            (GET) (/route) => {
+                let format = match get_format(request) {
+                    Ok(format) => format,
+                    Err(response) => return response,
+                };
+                let mut ports = Vec::new();
+                for param in ["from", "to"] {
+                    let locode = match request.get_param(param) {
+                        Some(locode) => locode,
+                        None => return Response::text(format!("Missing {} port", param)).with_status_code(400),
+                    };
+                    match port_registry.get(&locode) {
+                        Some(port) => ports.push(port),
+                        None => return Response::text(format!("Unknown port {}", locode)).with_status_code(404),
+                    }
+                }
+                let draft = match request.get_param("draft").map(|d| d.parse::<f64>()) {
+                    Some(Ok(draft)) => Some(draft),
+                    Some(Err(_)) => return Response::text("Invalid draft").with_status_code(400),
+                    None => None,
+                };
+                for port in ports.iter() {
+                    if let (Some(draft), Some(max_draft)) = (draft, port.max_draft) {
+                        if draft > max_draft {
+                            return Response::text(format!("Draft exceeds the maximum draft of {} ({} m)", port.name, max_draft)).with_status_code(400);
+                        }
+                    }
+                }
+                println!("Route from {} to {}", ports[0].locode, ports[1].locode);
+
+                let options = QueryOptions {
+                    exclusion_zones: exclusion_zones.clone(),
+                    penalty_zones: penalty_zones.clone(),
+                    draft,
+                    start_node: Some(ports[0].node),
+                    end_node: Some(ports[1].node),
+                    ..Default::default()
+                };
+                let mut state = AlgorithmState::new(graph.node_count());
+                let result = match &coarse_graph {
+                    Some(coarse_graph) => graph.find_path_in_corridor(coarse_graph, ports[0].lon, ports[0].lat, ports[1].lon, ports[1].lat, &execution_type, &options, &mut state),
+                    None => graph.find_path(ports[0].lon, ports[0].lat, ports[1].lon, ports[1].lat, &execution_type, &options, &mut state),
+                };
+                println!("Done!\n");
+                create_response(result, format, &format!("{} - {}", ports[0].name, ports[1].name))
+            },
+
+            (GET) (/nearest-ports) => {
+                let mut position = Vec::new();
+                for param in ["lon", "lat"] {
+                    match request.get_param(param).map(|c| c.parse::<f64>()) {
+                        Some(Ok(coordinate)) => position.push(coordinate),
+                        _ => return Response::text(format!("Missing or invalid {}", param)).with_status_code(400),
+                    }
                 }
-                Response::text("{}")
+                let k = match request.get_param("k").map(|k| k.parse::<usize>()) {
+                    Some(Ok(k)) => k,
+                    Some(Err(_)) => return Response::text("Invalid k").with_status_code(400),
+                    None => DEFAULT_NEAREST_PORTS,
+                };
+                let draft = match request.get_param("draft").map(|d| d.parse::<f64>()) {
+                    Some(Ok(draft)) => Some(draft),
+                    Some(Err(_)) => return Response::text("Invalid draft").with_status_code(400),
+                    None => None,
+                };
+                println!("Nearest {} ports of {},{}", k, position[0], position[1]);
+
+                let options = QueryOptions {
+                    exclusion_zones: exclusion_zones.clone(),
+                    penalty_zones: penalty_zones.clone(),
+                    draft,
+                    ..Default::default()
+                };
+                let mut state = AlgorithmState::new(graph.node_count());
+                let nearest_ports = graph.find_nearest_ports(position[0], position[1], &port_registry, k, &execution_type, &options, &mut state);
+                println!("Done!\n");
+                let response: Vec<NearestPortResponse> = nearest_ports
+                    .into_iter()
+                    .map(|(port, route)| match route {
+                        Ok(route) => NearestPortResponse { port, route: Some(create_route_response(route)), error: None },
+                        Err(error) => NearestPortResponse { port, route: None, error: Some(error) },
+                    })
+                    .collect();
+                Response::json(&response)
+            },
+
+            (GET) (/ports) => {
+                let query = request.get_param("q").unwrap_or_default();
+                Response::json(&port_registry.search(&query, MAX_PORT_SUGGESTIONS))
             },
 
             (GET) (/marker-icon) => {
@@ -112,3 +404,55 @@ fn main() {
         )
     });
 }
+
+fn parse_option(name: &str, value: &str) -> f64 {
+    value
+        .trim()
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid number {} for option {}", value, name))
+}
+
+fn get_format(request: &rouille::Request) -> Result<Option<ExportFormat>, Response> {
+    match request.get_param("format").filter(|f| !f.is_empty()) {
+        Some(format) => match ExportFormat::from_str(&format) {
+            Ok(format) => Ok(Some(format)),
+            Err(()) => Err(Response::text(format!(
+                "Invalid format, possible formats: {}",
+                ExportFormat::get_strings().join(", ")
+            ))
+            .with_status_code(400)),
+        },
+        None => Ok(None),
+    }
+}
+
+// The route as JSON for the web interface or as file in the requested format
+fn create_response(
+    result: Result<Route, String>,
+    format: Option<ExportFormat>,
+    name: &str,
+) -> Response {
+    match (result, format) {
+        (Ok(route), Some(format)) => Response::from_data(
+            format.get_content_type(),
+            export::export_route(&route.waypoints, name, &format),
+        )
+        .with_content_disposition_attachment(&format!("route.{}", format.get_extension())),
+        (Ok(route), None) => Response::json(&create_route_response(route)),
+        (Err(e), _) => Response::text(e).with_status_code(404),
+    }
+}
+
+fn create_route_response(route: Route) -> RouteResponse {
+    RouteResponse {
+        geojson: route.geojson,
+        distance: route.distance,
+        travel_time: route.travel_time,
+        zone_distances: route.zone_distances,
+        canal_transits: route.canal_transits,
+        emissions: route.emissions,
+        sea_areas: route.sea_areas,
+        start_snap_distance: route.start_snap_distance,
+        end_snap_distance: route.end_snap_distance,
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crate::{
+    AlgorithmState, ExecutionType, Graph, HeapNode, PathResult, QueryOptions, Route,
+    DEFAULT_SNAP_RADIUS,
+};
+
+#[derive(serde::Serialize)]
+pub struct Port {
+    pub name: String,
+    pub locode: String, // UN/LOCODE, e.g. NLRTM
+    pub lon: f64,
+    pub lat: f64,
+    pub max_draft: Option<f64>, // Meters
+    #[serde(skip)]
+    pub node: usize, // Nearest water node, see Graph::find_nearest_node
+}
+
+pub struct PortRegistry {
+    pub ports: Vec<Port>,
+}
+
+impl PortRegistry {
+    // Reads name,locode,lon,lat,max_draft lines and snaps every port to the graph once, the max draft may be empty
+    pub fn new_from_csvfile(filename: &str, graph: &Graph) -> Self {
+        println!("Creating port registry from csv file: {}", filename);
+        let content = fs::read_to_string(filename).unwrap();
+
+        let mut ports = Vec::new();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with("name,") {
+                continue;
+            }
+            // Split from the right, so that names may contain commas
+            let mut parts = line.rsplitn(5, ',').map(|p| p.trim());
+            let max_draft = parts.next().unwrap();
+            let lat: f64 = parts.next().unwrap().parse().unwrap();
+            let lon: f64 = parts.next().unwrap().parse().unwrap();
+            let locode = parts.next().unwrap().to_uppercase();
+            let name = parts.next().unwrap().to_string();
+
+            match graph.find_nearest_node(lon, lat) {
+                Some(node) => ports.push(Port {
+                    name,
+                    locode,
+                    lon,
+                    lat,
+                    max_draft: max_draft.parse().ok(),
+                    node,
+                }),
+                None => println!("No water node found for port {} ({})", name, locode),
+            }
+        }
+        println!("Created port registry with {} ports", ports.len());
+        PortRegistry { ports }
+    }
+
+    pub fn get(&self, locode: &str) -> Option<&Port> {
+        self.ports
+            .iter()
+            .find(|p| p.locode.eq_ignore_ascii_case(locode.trim()))
+    }
+
+    // Ports whose UN/LOCODE or name start with the query, followed by ports whose name contains it
+    pub fn search(&self, query: &str, limit: usize) -> Vec<&Port> {
+        let query = query.trim().to_lowercase();
+        let mut matches: Vec<(usize, &Port)> = self
+            .ports
+            .iter()
+            .filter_map(|p| {
+                let name = p.name.to_lowercase();
+                if p.locode.to_lowercase().starts_with(&query) {
+                    Some((0, p))
+                } else if name.starts_with(&query) {
+                    Some((1, p))
+                } else if name.contains(&query) {
+                    Some((2, p))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        matches.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.name.cmp(&b.1.name)));
+        matches.into_iter().take(limit).map(|(_, p)| p).collect()
+    }
+}
+
+impl Graph {
+    // The k ports with the shortest sailing distance from the position together with their routes. A Dijkstra
+    // from the position stops once k ports are settled, ports whose max draft is below the draft are skipped.
+    // The routes are taken from the shortest path tree of the Dijkstra, ports for which no route can be
+    // created are returned last with the reason. The Dijkstra uses the static weights, so with a departure
+    // time the ports are selected by them but sorted by the travel time of their final routes.
+    #[allow(clippy::too_many_arguments)]
+    pub fn find_nearest_ports<'a>(
+        &self,
+        lon: f64,
+        lat: f64,
+        port_registry: &'a PortRegistry,
+        k: usize,
+        execution_type: &ExecutionType,
+        options: &QueryOptions,
+        state: &mut AlgorithmState,
+    ) -> Vec<(&'a Port, Result<Route, String>)> {
+        self.apply_query_options(options, &mut state.restrictions);
+        let start = match self.find_nearest_node_within(
+            lon,
+            lat,
+            options.snap_radius.unwrap_or(DEFAULT_SNAP_RADIUS),
+            &state.restrictions,
+        ) {
+            Some((start, _)) => start,
+            None => {
+                println!("No nearest node found");
+                return Vec::new();
+            }
+        };
+
+        let mut port_nodes: HashMap<usize, Vec<&Port>> = HashMap::new();
+        for port in port_registry.ports.iter() {
+            if matches!((options.draft, port.max_draft), (Some(draft), Some(max_draft)) if draft > max_draft)
+            {
+                continue;
+            }
+            port_nodes.entry(port.node).or_default().push(port);
+        }
+
+        let mut nearest_ports = Vec::new();
+        state.reset_dijkstra();
+        state.distances[start] = 0;
+        state.queue.push(HeapNode {
+            id: start as u32,
+            distance: 0,
+        });
+        while let Some(node) = state.queue.pop() {
+            if node.distance > state.distances[node.id as usize] {
+                continue;
+            }
+            if let Some(ports) = port_nodes.get(&(node.id as usize)) {
+                nearest_ports.extend(ports.iter().copied());
+                if nearest_ports.len() >= k {
+                    break;
+                }
+            }
+
+            for i in self.offsets[node.id as usize].0 as usize
+                ..self.offsets[node.id as usize + 1].0 as usize
+            {
+                let dest = self.edges[i].destination;
+                if state.restrictions.is_blocked(i, dest as usize) {
+                    continue;
+                }
+                let new_distance = node.distance
+                    + state.restrictions.get_weight(
+                        node.id as usize,
+                        dest as usize,
+                        self.edges[i].distance,
+                    );
+                if new_distance < state.distances[dest as usize] {
+                    state.queue.push(HeapNode {
+                        id: dest,
+                        distance: new_distance,
+                    });
+                    state.distances[dest as usize] = new_distance;
+                    state.parent_nodes[dest as usize] = node.id;
+                }
+            }
+        }
+        nearest_ports.truncate(k);
+        let distances = state.distances.clone();
+        let parent_nodes = state.parent_nodes.clone();
+
+        // The routes are created like any other query, which also adds the first and last miles
+        let mut routes = Vec::new();
+        for port in nearest_ports {
+            let port_options = QueryOptions {
+                start_node: Some(start),
+                end_node: Some(port.node),
+                ..options.clone()
+            };
+            let route = self.find_path_with(
+                lon,
+                lat,
+                port.lon,
+                port.lat,
+                &port_options,
+                state,
+                |graph, path_start, path_end, start_time, state| {
+                    // The first or last mile may have moved the node around the coast, which isn't settled then
+                    if path_start != start || path_end != port.node {
+                        return match execution_type {
+                            ExecutionType::AStar | ExecutionType::ShortcutAStar => {
+                                graph.a_star(path_start, path_end, state)
+                            }
+                            _ => graph.dijkstra(path_start, path_end, state),
+                        };
+                    }
+                    let mut path = vec![path_end];
+                    while *path.last().unwrap() != start {
+                        path.push(parent_nodes[*path.last().unwrap()] as usize);
+                    }
+                    // The tree is built without weather, so the travel time is calculated along the path
+                    let distance = match start_time {
+                        Some(start_time) => graph.calculate_legs_weight(
+                            &path
+                                .iter()
+                                .rev()
+                                .map(|n| [graph.get_lon(*n), graph.get_lat(*n)])
+                                .collect::<Vec<_>>(),
+                            Some(start_time),
+                        ),
+                        None => distances[path_end],
+                    };
+                    PathResult {
+                        path: Some(path),
+                        distance: Some(distance),
+                        heap_pops: 0,
+                    }
+                },
+            );
+            if let Err(error) = &route {
+                println!(
+                    "No route to port {} ({}): {}",
+                    port.name, port.locode, error
+                );
+            }
+            routes.push((port, route));
+        }
+        let get_cost = |route: &Result<Route, String>| match route {
+            Ok(route) => route.travel_time.unwrap_or(route.distance),
+            Err(_) => f64::INFINITY,
+        };
+        routes.sort_by(|a, b| get_cost(&a.1).total_cmp(&get_cost(&b.1)));
+        routes
+    }
+}
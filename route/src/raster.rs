@@ -0,0 +1,116 @@
+use std::fs;
+
+// Grid in the ESRI ASCII raster format (.asc), e.g. GEBCO bathymetry or sea ice concentrations
+pub struct Raster {
+    pub columns_count: usize,
+    pub rows_count: usize,
+    pub min_lon: f64,
+    pub min_lat: f64,
+    pub cell_size: f64,
+    pub values: Vec<f32>, // Rows from north to south, NaN if no data is available
+}
+
+impl Raster {
+    pub fn new_from_ascfile(filename: &str) -> Self {
+        println!("Creating raster from asc file: {}", filename);
+        let raster = Self::new_from_asc(&fs::read_to_string(filename).unwrap()).unwrap();
+        println!(
+            "Created raster with {}x{} cells",
+            raster.columns_count, raster.rows_count
+        );
+        raster
+    }
+
+    pub fn new_from_asc(content: &str) -> Result<Self, String> {
+        let mut columns_count = None;
+        let mut rows_count = None;
+        let mut min_lon = None;
+        let mut min_lat = None;
+        let mut is_center = false;
+        let mut cell_size = None;
+        let mut no_data = None;
+
+        let mut tokens = content.split_whitespace().peekable();
+        while let Some(key) = tokens.next_if(|t| t.starts_with(char::is_alphabetic)) {
+            let value: f64 = tokens
+                .next()
+                .and_then(|v| v.parse().ok())
+                .ok_or(format!("Invalid value for {}", key))?;
+            match key.to_lowercase().as_str() {
+                "ncols" => columns_count = Some(value as usize),
+                "nrows" => rows_count = Some(value as usize),
+                "xllcorner" => min_lon = Some(value),
+                "yllcorner" => min_lat = Some(value),
+                "xllcenter" => {
+                    min_lon = Some(value);
+                    is_center = true;
+                }
+                "yllcenter" => {
+                    min_lat = Some(value);
+                    is_center = true;
+                }
+                "cellsize" => cell_size = Some(value),
+                "nodata_value" => no_data = Some(value),
+                _ => return Err(format!("Unknown header {}", key)),
+            }
+        }
+
+        let columns_count = columns_count.ok_or("Missing ncols")?;
+        let rows_count = rows_count.ok_or("Missing nrows")?;
+        let cell_size = cell_size.ok_or("Missing cellsize")?;
+        let mut min_lon = min_lon.ok_or("Missing xllcorner")?;
+        let mut min_lat = min_lat.ok_or("Missing yllcorner")?;
+        if is_center {
+            min_lon -= cell_size / 2.0;
+            min_lat -= cell_size / 2.0;
+        }
+
+        let mut values = Vec::with_capacity(columns_count * rows_count);
+        for token in tokens {
+            let value: f64 = token
+                .parse()
+                .map_err(|_| format!("Invalid raster value {}", token))?;
+            if Some(value) == no_data {
+                values.push(f32::NAN);
+            } else {
+                values.push(value as f32);
+            }
+        }
+        if values.len() != columns_count * rows_count {
+            return Err(format!(
+                "Expected {} raster values but found {}",
+                columns_count * rows_count,
+                values.len()
+            ));
+        }
+
+        Ok(Raster {
+            columns_count,
+            rows_count,
+            min_lon,
+            min_lat,
+            cell_size,
+            values,
+        })
+    }
+
+    // Value of the cell containing the position, None outside of the raster or if no data is available
+    pub fn sample(&self, lon: f64, lat: f64) -> Option<f64> {
+        let column = ((lon - self.min_lon) / self.cell_size).floor();
+        let row = self.rows_count as f64 - 1.0 - ((lat - self.min_lat) / self.cell_size).floor();
+        if column < 0.0
+            || row < 0.0
+            || column >= self.columns_count as f64
+            || row >= self.rows_count as f64
+        {
+            return None;
+        }
+
+        let value = self.values[row as usize * self.columns_count + column as usize];
+        if value.is_nan() {
+            None
+        } else {
+            Some(value as f64)
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use crate::zones::{self, Zone};
+use crate::{Graph, METERS_PER_NAUTICAL_MILE};
+
+#[derive(serde::Serialize)]
+pub struct SeaAreaDistance {
+    pub name: String,
+    pub distance: f64, // Nautical miles
+}
+
+impl Graph {
+    // Tags every water node with the first sea area containing it, names are taken from the name or NAME
+    // property (as in the IHO sea areas)
+    pub fn set_sea_areas(&mut self, sea_areas: &[Zone]) {
+        println!("Tagging nodes with {} sea areas", sea_areas.len());
+        let node_count = self.offsets.len() - 1;
+        self.sea_areas = vec![0; node_count];
+        self.sea_area_names = Vec::new();
+
+        for (i, sea_area) in sea_areas.iter().enumerate() {
+            let name = sea_area
+                .name
+                .clone()
+                .or_else(|| {
+                    sea_area
+                        .properties
+                        .get("NAME")
+                        .and_then(|n| n.as_str())
+                        .map(|n| n.to_string())
+                })
+                .unwrap_or_else(|| format!("Sea area {}", i + 1));
+            self.sea_area_names.push(name);
+
+            for node in self.get_nodes_in_bounding_box(sea_area.get_bounding_box()) {
+                if self.sea_areas[node] == 0
+                    && self.offsets[node] != self.offsets[node + 1]
+                    && sea_area.contains(self.get_lon(node), self.get_lat(node))
+                {
+                    self.sea_areas[node] = i as u16 + 1;
+                }
+            }
+        }
+
+        let tagged_count = self.sea_areas.iter().filter(|a| **a != 0).count();
+        println!("Tagged {} nodes with a sea area", tagged_count);
+    }
+
+    pub fn get_sea_area(&self, lon: f64, lat: f64) -> Option<&str> {
+        if self.sea_areas.is_empty() {
+            return None;
+        }
+        match self.sea_areas[self.get_node_at(lon, lat)] {
+            0 => None,
+            area => Some(&self.sea_area_names[area as usize - 1]),
+        }
+    }
+
+    // Sea areas traversed along the coordinates in order, with the distance sailed in each. Parts without
+    // a sea area are left out.
+    pub fn measure_sea_areas(&self, coordinates: &[[f64; 2]]) -> Vec<SeaAreaDistance> {
+        let mut sea_areas: Vec<SeaAreaDistance> = Vec::new();
+        if self.sea_areas.is_empty() {
+            return sea_areas;
+        }
+
+        for segment in coordinates.windows(2) {
+            let (length, samples) = zones::sample_segment(segment[0], segment[1]);
+            for [lon, lat] in samples.iter() {
+                let name = match self.get_sea_area(*lon, *lat) {
+                    Some(name) => name,
+                    None => continue,
+                };
+                let distance = length / samples.len() as f64 / METERS_PER_NAUTICAL_MILE;
+                match sea_areas.last_mut() {
+                    Some(last) if last.name == name => last.distance += distance,
+                    _ => sea_areas.push(SeaAreaDistance {
+                        name: name.to_string(),
+                        distance,
+                    }),
+                }
+            }
+        }
+        sea_areas
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{Graph, Restrictions};
+
+// Nodes of graphs with explicit coordinates bucketed into cells of equal size in degrees, rows start at
+// lat 90 like the raster
+pub struct SpatialIndex {
+    pub max_edge_extent: (f64, f64), // Largest lon and lat difference between the nodes of an edge
+    cell_size: f64,
+    columns_count: usize,
+    rows_count: usize,
+    cells: Vec<Vec<u32>>,
+}
+
+impl SpatialIndex {
+    pub fn new(coordinates: &[[f64; 2]], cell_size: f64, max_edge_extent: (f64, f64)) -> Self {
+        let columns_count = (360.0 / cell_size).ceil() as usize;
+        let rows_count = (180.0 / cell_size).ceil() as usize;
+        let mut index = SpatialIndex {
+            max_edge_extent,
+            cell_size,
+            columns_count,
+            rows_count,
+            cells: vec![Vec::new(); columns_count * rows_count],
+        };
+        for (node, [lon, lat]) in coordinates.iter().enumerate() {
+            let cell = index.get_row(*lat) * columns_count + index.get_column(*lon);
+            index.cells[cell].push(node as u32);
+        }
+        index
+    }
+
+    fn get_column(&self, lon: f64) -> usize {
+        (((lon + 180.0) / self.cell_size).floor() as isize).rem_euclid(self.columns_count as isize)
+            as usize
+    }
+
+    fn get_row(&self, lat: f64) -> usize {
+        (((90.0 - lat) / self.cell_size).floor().max(0.0) as usize).min(self.rows_count - 1)
+    }
+
+    // Nodes of all cells overlapping the bounding box, which may extend across the antimeridian
+    pub fn get_nodes_in_bounding_box(&self, bounding_box: (f64, f64, f64, f64)) -> Vec<usize> {
+        let (min_lon, min_lat, max_lon, max_lat) = bounding_box;
+        let first_column = ((min_lon + 180.0) / self.cell_size).floor() as isize;
+        let last_column = if max_lon - min_lon >= 360.0 {
+            first_column + self.columns_count as isize - 1
+        } else {
+            ((max_lon + 180.0) / self.cell_size).floor() as isize
+        };
+
+        let mut nodes = Vec::new();
+        for row in self.get_row(max_lat)..=self.get_row(min_lat) {
+            for column in first_column..=last_column {
+                let column = column.rem_euclid(self.columns_count as isize) as usize;
+                nodes.extend(
+                    self.cells[row * self.columns_count + column]
+                        .iter()
+                        .map(|n| *n as usize),
+                );
+            }
+        }
+        nodes
+    }
+}
+
+impl Graph {
+    // Needed by the nearest node and bounding box queries of graphs with explicit coordinates
+    pub fn build_spatial_index(&mut self) {
+        if self.is_raster() {
+            return;
+        }
+        let cell_size = 2.0 * self.get_lat_step();
+        let mut max_edge_extent = (0.0, 0.0);
+        for node in 0..self.node_count() {
+            let [lon, lat] = self.coordinates[node];
+            for e in self.offsets[node].0..self.offsets[node + 1].0 {
+                let [dest_lon, dest_lat] =
+                    self.coordinates[self.edges[e as usize].destination as usize];
+                let lon_difference = (lon - dest_lon).abs();
+                max_edge_extent.0 = f64::max(
+                    max_edge_extent.0,
+                    lon_difference.min(360.0 - lon_difference),
+                );
+                max_edge_extent.1 = f64::max(max_edge_extent.1, (lat - dest_lat).abs());
+            }
+        }
+        self.spatial_index = Some(SpatialIndex::new(
+            &self.coordinates,
+            cell_size,
+            max_edge_extent,
+        ));
+    }
+
+    pub fn get_spatial_index(&self) -> &SpatialIndex {
+        self.spatial_index
+            .as_ref()
+            .expect("Graphs with explicit coordinates need a spatial index")
+    }
+
+    // Same as find_nearest_node_within for graphs with explicit coordinates
+    pub fn find_nearest_indexed_node_within(
+        &self,
+        lon: f64,
+        lat: f64,
+        radius: f64,
+        restrictions: &Restrictions,
+    ) -> Option<(usize, u32)> {
+        let lat = lat.clamp(-90.0, 90.0);
+        let lat_radius = (radius / 6371000.0).to_degrees();
+        let max_lat = lat.abs() + lat_radius;
+        let lon_radius = if max_lat >= 90.0 {
+            180.0
+        } else {
+            (lat_radius / max_lat.to_radians().cos()).min(180.0)
+        };
+
+        let mut best_node = None;
+        let mut best_largest_component_node = None;
+        for node in self.get_spatial_index().get_nodes_in_bounding_box((
+            lon - lon_radius,
+            lat - lat_radius,
+            lon + lon_radius,
+            lat + lat_radius,
+        )) {
+            if self.offsets[node] == self.offsets[node + 1] || restrictions.is_node_blocked(node) {
+                continue;
+            }
+            let distance =
+                Self::calculate_distance(lon, lat, self.get_lon(node), self.get_lat(node));
+            if distance as f64 > radius {
+                continue;
+            }
+            if best_node.is_none_or(|(_, d)| distance < d) {
+                best_node = Some((node, distance));
+            }
+            if self.is_in_largest_component(node)
+                && best_largest_component_node.is_none_or(|(_, d)| distance < d)
+            {
+                best_largest_component_node = Some((node, distance));
+            }
+        }
+
+        best_largest_component_node.or(best_node)
+    }
+
+    // Closest water or land node of a graph with explicit coordinates, the search area is doubled until a
+    // node is found. A closer node can lie outside of the box of the first hit, so the search area is
+    // doubled once more before returning.
+    pub fn find_nearest_indexed_node(&self, lon: f64, lat: f64) -> usize {
+        let mut radius = 2.0 * self.get_lat_step();
+        let mut found = false;
+        loop {
+            let nodes = self.get_spatial_index().get_nodes_in_bounding_box((
+                lon - radius,
+                lat - radius,
+                lon + radius,
+                lat + radius,
+            ));
+            let nearest = nodes.into_iter().min_by_key(|node| {
+                Self::calculate_distance(lon, lat, self.get_lon(*node), self.get_lat(*node))
+            });
+            match nearest {
+                Some(node) if found || radius >= 360.0 => return node,
+                Some(_) => found = true,
+                None if radius >= 360.0 => return 0,
+                None => {}
+            }
+            radius *= 2.0;
+        }
+    }
+}
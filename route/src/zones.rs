@@ -0,0 +1,513 @@
+use std::fs;
+
+use serde_json::{Map, Value};
+
+use crate::{Graph, Restrictions, METERS_PER_NAUTICAL_MILE};
+
+// Polygon in lon/lat coordinates, the first ring is the outer boundary and all further rings are holes
+#[derive(Clone)]
+pub struct Polygon {
+    pub rings: Vec<Vec<[f64; 2]>>,
+    pub bounding_box: (f64, f64, f64, f64), // min lon, min lat, max lon, max lat
+}
+
+impl Polygon {
+    pub fn new(rings: Vec<Vec<[f64; 2]>>) -> Self {
+        let mut bounding_box = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+        for point in rings[0].iter() {
+            bounding_box.0 = bounding_box.0.min(point[0]);
+            bounding_box.1 = bounding_box.1.min(point[1]);
+            bounding_box.2 = bounding_box.2.max(point[0]);
+            bounding_box.3 = bounding_box.3.max(point[1]);
+        }
+        Polygon {
+            rings,
+            bounding_box,
+        }
+    }
+
+    pub fn contains(&self, lon: f64, lat: f64) -> bool {
+        if lon < self.bounding_box.0
+            || lat < self.bounding_box.1
+            || lon > self.bounding_box.2
+            || lat > self.bounding_box.3
+        {
+            return false;
+        }
+
+        // Ray casting, points inside of holes are crossed an even number of times
+        let mut is_inside = false;
+        for ring in self.rings.iter() {
+            for i in 0..ring.len() {
+                let first = ring[i];
+                let second = ring[(i + 1) % ring.len()];
+                if (first[1] > lat) != (second[1] > lat)
+                    && lon
+                        < first[0]
+                            + (lat - first[1]) / (second[1] - first[1]) * (second[0] - first[0])
+                {
+                    is_inside = !is_inside;
+                }
+            }
+        }
+        is_inside
+    }
+
+    pub fn intersects_segment(&self, lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> bool {
+        // Segments crossing the antimeridian are checked on both sides of it
+        if (lon1 - lon2).abs() > 180.0 {
+            let shift = if lon1 < lon2 { 360.0 } else { -360.0 };
+            return self.intersects_segment(lon1, lat1, lon2 - shift, lat2)
+                || self.intersects_segment(lon1 + shift, lat1, lon2, lat2);
+        }
+
+        if lon1.max(lon2) < self.bounding_box.0
+            || lat1.max(lat2) < self.bounding_box.1
+            || lon1.min(lon2) > self.bounding_box.2
+            || lat1.min(lat2) > self.bounding_box.3
+        {
+            return false;
+        }
+        if self.contains(lon1, lat1) || self.contains(lon2, lat2) {
+            return true;
+        }
+
+        for ring in self.rings.iter() {
+            for i in 0..ring.len() {
+                let first = ring[i];
+                let second = ring[(i + 1) % ring.len()];
+                if segments_intersect([lon1, lat1], [lon2, lat2], first, second) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+pub fn segments_intersect(p1: [f64; 2], p2: [f64; 2], q1: [f64; 2], q2: [f64; 2]) -> bool {
+    let orientation = |a: [f64; 2], b: [f64; 2], c: [f64; 2]| {
+        let cross = (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]);
+        if cross > 0.0 {
+            1
+        } else if cross < 0.0 {
+            -1
+        } else {
+            0
+        }
+    };
+    let on_segment = |a: [f64; 2], b: [f64; 2], c: [f64; 2]| {
+        c[0] >= a[0].min(b[0])
+            && c[0] <= a[0].max(b[0])
+            && c[1] >= a[1].min(b[1])
+            && c[1] <= a[1].max(b[1])
+    };
+
+    let o1 = orientation(p1, p2, q1);
+    let o2 = orientation(p1, p2, q2);
+    let o3 = orientation(q1, q2, p1);
+    let o4 = orientation(q1, q2, p2);
+    if o1 != o2 && o3 != o4 {
+        return true;
+    }
+    (o1 == 0 && on_segment(p1, p2, q1))
+        || (o2 == 0 && on_segment(p1, p2, q2))
+        || (o3 == 0 && on_segment(q1, q2, p1))
+        || (o4 == 0 && on_segment(q1, q2, p2))
+}
+
+// Area described by a GeoJSON feature, e.g. a closed strait or a sanctioned region
+#[derive(Clone)]
+pub struct Zone {
+    pub name: Option<String>,
+    pub polygons: Vec<Polygon>,
+    pub properties: Map<String, Value>,
+}
+
+impl Zone {
+    pub fn contains(&self, lon: f64, lat: f64) -> bool {
+        self.polygons.iter().any(|p| p.contains(lon, lat))
+    }
+
+    pub fn intersects_segment(&self, lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> bool {
+        self.polygons
+            .iter()
+            .any(|p| p.intersects_segment(lon1, lat1, lon2, lat2))
+    }
+
+    pub fn get_bounding_box(&self) -> (f64, f64, f64, f64) {
+        let mut bounding_box = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+        for polygon in self.polygons.iter() {
+            bounding_box.0 = bounding_box.0.min(polygon.bounding_box.0);
+            bounding_box.1 = bounding_box.1.min(polygon.bounding_box.1);
+            bounding_box.2 = bounding_box.2.max(polygon.bounding_box.2);
+            bounding_box.3 = bounding_box.3.max(polygon.bounding_box.3);
+        }
+        bounding_box
+    }
+
+    // Multiplier for distances sailed inside of the zone, never below 1 to keep the A* heuristics admissible
+    pub fn get_cost_factor(&self) -> f64 {
+        self.properties
+            .get("cost_factor")
+            .and_then(|f| f.as_f64())
+            .unwrap_or(1.0)
+            .max(1.0)
+    }
+
+    // Fixed costs for entering the zone, given in nautical miles and returned in meters
+    pub fn get_entry_penalty(&self) -> u32 {
+        let penalty = self
+            .properties
+            .get("entry_penalty")
+            .and_then(|p| p.as_f64())
+            .unwrap_or(0.0)
+            .max(0.0);
+        (penalty * METERS_PER_NAUTICAL_MILE) as u32
+    }
+
+    pub fn new_from_geojson_file(filename: &str) -> Vec<Zone> {
+        println!("Creating zones from geojson file: {}", filename);
+        let zones = Self::new_from_geojson(&fs::read_to_string(filename).unwrap()).unwrap();
+        println!("Created {} zones", zones.len());
+        zones
+    }
+
+    // Accepts a FeatureCollection, a single Feature or a bare Polygon/MultiPolygon geometry
+    pub fn new_from_geojson(geojson: &str) -> Result<Vec<Zone>, String> {
+        let value: Value = serde_json::from_str(geojson).map_err(|e| e.to_string())?;
+        let mut zones = Vec::new();
+        match value["type"].as_str() {
+            Some("FeatureCollection") => {
+                let features = value["features"]
+                    .as_array()
+                    .ok_or("FeatureCollection without features")?;
+                for feature in features {
+                    zones.push(parse_feature(feature)?);
+                }
+            }
+            Some("Feature") => zones.push(parse_feature(&value)?),
+            Some(_) => zones.push(Zone {
+                name: None,
+                polygons: parse_geometry(&value)?,
+                properties: Map::new(),
+            }),
+            None => return Err("Missing GeoJSON type".to_string()),
+        }
+        Ok(zones)
+    }
+}
+
+fn parse_feature(feature: &Value) -> Result<Zone, String> {
+    let properties = feature["properties"]
+        .as_object()
+        .cloned()
+        .unwrap_or_default();
+    Ok(Zone {
+        name: properties
+            .get("name")
+            .and_then(|n| n.as_str())
+            .map(|n| n.to_string()),
+        polygons: parse_geometry(&feature["geometry"])?,
+        properties,
+    })
+}
+
+fn parse_geometry(geometry: &Value) -> Result<Vec<Polygon>, String> {
+    match geometry["type"].as_str() {
+        Some("Polygon") => Ok(vec![parse_polygon(&geometry["coordinates"])?]),
+        Some("MultiPolygon") => geometry["coordinates"]
+            .as_array()
+            .ok_or("Invalid MultiPolygon coordinates")?
+            .iter()
+            .map(parse_polygon)
+            .collect(),
+        Some(t) => Err(format!("Unsupported geometry type {}", t)),
+        None => Err("Missing geometry type".to_string()),
+    }
+}
+
+fn parse_polygon(coordinates: &Value) -> Result<Polygon, String> {
+    let mut rings = Vec::new();
+    for ring in coordinates
+        .as_array()
+        .ok_or("Invalid Polygon coordinates")?
+    {
+        let mut points = Vec::new();
+        for point in ring.as_array().ok_or("Invalid Polygon ring")? {
+            match (point[0].as_f64(), point[1].as_f64()) {
+                (Some(lon), Some(lat)) => points.push([lon, lat]),
+                _ => return Err("Invalid Polygon coordinate".to_string()),
+            }
+        }
+        if points.len() < 3 {
+            return Err("Polygon rings need at least three coordinates".to_string());
+        }
+        rings.push(points);
+    }
+    if rings.is_empty() {
+        return Err("Polygon without rings".to_string());
+    }
+    Ok(Polygon::new(rings))
+}
+
+impl Graph {
+    // Nodes in the bounding box including one additional row/column on each side. Graphs with explicit
+    // coordinates can have long edges, so the box is extended by the largest edge, which includes the nodes
+    // of all edges crossing the box.
+    pub fn get_nodes_in_bounding_box(&self, bounding_box: (f64, f64, f64, f64)) -> Vec<usize> {
+        if !self.is_raster() {
+            let spatial_index = self.get_spatial_index();
+            let (lon_extent, lat_extent) = spatial_index.max_edge_extent;
+            return spatial_index.get_nodes_in_bounding_box((
+                bounding_box.0 - lon_extent,
+                bounding_box.1 - lat_extent,
+                bounding_box.2 + lon_extent,
+                bounding_box.3 + lat_extent,
+            ));
+        }
+        let (min_lon, min_lat, max_lon, max_lat) = bounding_box;
+        let (raster_min_lon, _, _, raster_max_lat) = self.bounding_box;
+        let lon_step = self.get_lon_step();
+        let lat_step = self.get_lat_step();
+
+        let first_column =
+            (((min_lon - raster_min_lon) / lon_step).floor() as isize - 1).max(0) as usize;
+        let last_column = ((((max_lon - raster_min_lon) / lon_step).ceil() as isize + 1).max(0)
+            as usize)
+            .min(self.raster_columns_count - 1);
+        let first_row =
+            (((raster_max_lat - max_lat) / lat_step).floor() as isize - 1).max(0) as usize;
+        let last_row = ((((raster_max_lat - min_lat) / lat_step).ceil() as isize + 1).max(0)
+            as usize)
+            .min(self.raster_rows_count - 1);
+
+        let mut nodes = Vec::new();
+        for row in first_row..=last_row {
+            for column in first_column..=last_column {
+                nodes.push(row * self.raster_columns_count + column);
+            }
+        }
+        nodes
+    }
+
+    // Blocks all nodes inside of the zones as well as all edges crossing them
+    pub fn block_zones(&self, zones: &[Zone], restrictions: &mut Restrictions) {
+        for zone in zones.iter() {
+            for node in self.get_nodes_in_bounding_box(zone.get_bounding_box()) {
+                let lon = self.get_lon(node);
+                let lat = self.get_lat(node);
+                if zone.contains(lon, lat) {
+                    restrictions.block_node(node, self.offsets.len() - 1);
+                }
+
+                for e in self.offsets[node].0 as usize..self.offsets[node + 1].0 as usize {
+                    let dest = self.edges[e].destination as usize;
+                    if zone.intersects_segment(lon, lat, self.get_lon(dest), self.get_lat(dest)) {
+                        restrictions.block_edge(e, self.edges.len());
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Graph {
+    // Assigns each node to the most expensive penalty zone containing it. Edges crossing a zone without a node
+    // inside of it, e.g. long edges of graphs with explicit coordinates, assign both of their nodes to the zone.
+    pub fn penalize_zones(&self, zones: &[Zone], restrictions: &mut Restrictions) {
+        if zones.is_empty() {
+            return;
+        }
+        restrictions.node_zones = vec![0; self.offsets.len() - 1];
+        restrictions.zone_costs = zones
+            .iter()
+            .map(|z| (z.get_cost_factor(), z.get_entry_penalty()))
+            .collect();
+
+        for (i, zone) in zones.iter().enumerate() {
+            let mut zone_nodes = Vec::new();
+            for node in self.get_nodes_in_bounding_box(zone.get_bounding_box()) {
+                let lon = self.get_lon(node);
+                let lat = self.get_lat(node);
+                if zone.contains(lon, lat) {
+                    zone_nodes.push(node);
+                    continue;
+                }
+                for e in self.offsets[node].0 as usize..self.offsets[node + 1].0 as usize {
+                    let dest = self.edges[e].destination as usize;
+                    let (dest_lon, dest_lat) = (self.get_lon(dest), self.get_lat(dest));
+                    if !zone.contains(dest_lon, dest_lat)
+                        && zone.intersects_segment(lon, lat, dest_lon, dest_lat)
+                    {
+                        zone_nodes.extend([node, dest]);
+                    }
+                }
+            }
+            for node in zone_nodes {
+                let current_zone = restrictions.node_zones[node];
+                if current_zone == 0
+                    || restrictions.zone_costs[current_zone as usize - 1].0
+                        < restrictions.zone_costs[i].0
+                {
+                    restrictions.node_zones[node] = i as u16 + 1;
+                }
+            }
+        }
+    }
+}
+
+// Distance in meters sailed inside of each zone along the line, sampled in steps of at most one nautical mile
+pub fn measure_zone_distances(coordinates: &[[f64; 2]], zones: &[Zone]) -> Vec<f64> {
+    let mut distances = vec![0.0; zones.len()];
+    if zones.is_empty() {
+        return distances;
+    }
+
+    for segment in coordinates.windows(2) {
+        let (length, samples) = sample_segment(segment[0], segment[1]);
+        for [lon, lat] in samples.iter() {
+            for (i, zone) in zones.iter().enumerate() {
+                if zone.contains(*lon, *lat) {
+                    distances[i] += length / samples.len() as f64;
+                }
+            }
+        }
+    }
+    distances
+}
+
+// Length of the segment in meters and the centers of its parts of at most one nautical mile
+pub fn sample_segment(start: [f64; 2], end: [f64; 2]) -> (f64, Vec<[f64; 2]>) {
+    let [lon1, lat1] = start;
+    let [mut lon2, lat2] = end;
+    if lon2 - lon1 > 180.0 {
+        lon2 -= 360.0;
+    } else if lon1 - lon2 > 180.0 {
+        lon2 += 360.0;
+    }
+
+    let length = Graph::calculate_distance(lon1, lat1, lon2, lat2) as f64;
+    let steps = (length / METERS_PER_NAUTICAL_MILE).ceil().max(1.0) as usize;
+    let mut samples = Vec::with_capacity(steps);
+    for step in 0..steps {
+        let factor = (step as f64 + 0.5) / steps as f64;
+        let mut lon = lon1 + (lon2 - lon1) * factor;
+        if lon > 180.0 {
+            lon -= 360.0;
+        } else if lon < -180.0 {
+            lon += 360.0;
+        }
+        samples.push([lon, lat1 + (lat2 - lat1) * factor]);
+    }
+    (length, samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Edge, Restrictions};
+
+    // Quadtree like graph with a dense block of nodes and two far apart nodes, whose edges cross a small zone
+    // between them without any node in its cell of the spatial index
+    fn create_graph() -> Graph {
+        let mut coordinates = vec![[0.0, 0.0], [16.0, 0.0]];
+        for row in 0..20 {
+            for column in 0..20 {
+                coordinates.push([20.0 + column as f64, 20.0 + row as f64]);
+            }
+        }
+        let mut graph = Graph {
+            bounding_box: (0.0, 0.0, 40.0, 40.0),
+            coordinates,
+            ..Default::default()
+        };
+        graph.offsets = vec![(0, None), (1, None)];
+        graph.offsets.extend((0..401).map(|_| (2, None)));
+        graph.edges = vec![
+            Edge {
+                destination: 1,
+                distance: 960000,
+            },
+            Edge {
+                destination: 0,
+                distance: 960000,
+            },
+        ];
+        graph.build_spatial_index();
+        graph
+    }
+
+    fn create_zone(properties: &str) -> Vec<Zone> {
+        Zone::new_from_geojson(&format!(
+            r#"{{"type": "Feature", "properties": {{{}}}, "geometry": {{"type": "Polygon",
+            "coordinates": [[[7.5, -0.5], [8.5, -0.5], [8.5, 0.5], [7.5, 0.5], [7.5, -0.5]]]}}}}"#,
+            properties
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn block_zones_blocks_long_edges_crossing_the_zone() {
+        let graph = create_graph();
+        let mut restrictions = Restrictions::default();
+        graph.block_zones(&create_zone(""), &mut restrictions);
+
+        assert!(restrictions.is_edge_blocked(0));
+        assert!(restrictions.is_edge_blocked(1));
+        assert!(!restrictions.is_node_blocked(0));
+        assert!(!restrictions.is_node_blocked(1));
+    }
+
+    #[test]
+    fn penalize_zones_assigns_nodes_of_long_edges_crossing_the_zone() {
+        let graph = create_graph();
+        let mut restrictions = Restrictions::default();
+        graph.penalize_zones(&create_zone(r#""cost_factor": 2"#), &mut restrictions);
+
+        assert_eq!(restrictions.node_zones[0], 1);
+        assert_eq!(restrictions.node_zones[1], 1);
+        assert_eq!(restrictions.node_zones[2], 0);
+    }
+
+    #[test]
+    fn polygon_contains_with_hole() {
+        let polygon = Polygon::new(vec![
+            vec![
+                [0.0, 0.0],
+                [10.0, 0.0],
+                [10.0, 10.0],
+                [0.0, 10.0],
+                [0.0, 0.0],
+            ],
+            vec![[4.0, 4.0], [6.0, 4.0], [6.0, 6.0], [4.0, 6.0], [4.0, 4.0]],
+        ]);
+        assert!(polygon.contains(2.0, 2.0));
+        assert!(!polygon.contains(5.0, 5.0));
+        assert!(!polygon.contains(11.0, 5.0));
+        assert!(!polygon.contains(-1.0, -1.0));
+    }
+
+    #[test]
+    fn segments_intersect_crossing_and_disjoint() {
+        assert!(segments_intersect(
+            [0.0, 0.0],
+            [2.0, 2.0],
+            [0.0, 2.0],
+            [2.0, 0.0]
+        ));
+        assert!(!segments_intersect(
+            [0.0, 0.0],
+            [1.0, 1.0],
+            [2.0, 0.0],
+            [3.0, 1.0]
+        ));
+        // Parallel segments
+        assert!(!segments_intersect(
+            [0.0, 0.0],
+            [2.0, 0.0],
+            [0.0, 1.0],
+            [2.0, 1.0]
+        ));
+    }
+}
@@ -0,0 +1,42 @@
+use std::{env, process};
+
+use route::Graph;
+
+const DEFAULT_SHORTCUT_SAMPLES: usize = 1000;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    if args.len() < 2 {
+        println!("Required: <Graph binary file> [number of sampled shortcut edges]");
+        return;
+    }
+    let shortcut_samples = match args.get(2) {
+        Some(samples) => samples.parse().unwrap(),
+        None => DEFAULT_SHORTCUT_SAMPLES,
+    };
+
+    let graph = Graph::new_from_binfile(&args[1]);
+    let checks = graph.check_integrity(shortcut_samples);
+
+    println!();
+    for check in checks.iter() {
+        if check.issue_count == 0 {
+            println!("{}: ok", check.name);
+            continue;
+        }
+        println!("{}: {} issues", check.name, check.issue_count);
+        for issue in check.issues.iter() {
+            println!("  {}", issue);
+        }
+        if check.issue_count > check.issues.len() {
+            println!("  ... {} more", check.issue_count - check.issues.len());
+        }
+    }
+
+    if checks.iter().any(|c| c.issue_count > 0) {
+        println!("The graph is invalid");
+        process::exit(1);
+    }
+    println!("The graph is valid");
+}
@@ -0,0 +1,94 @@
+use std::{env, fs, str::FromStr};
+
+use route::import;
+use route::zones::Zone;
+use route::{AlgorithmState, ExecutionType, Graph};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    if args.len() < 3 {
+        println!("Required: <Graph binary file> <route file (gpx, rtz or geojson)>");
+        println!("Options:");
+        println!("  --coastlines <geojson file>  Coastline polygons written by extract");
+        println!("  --algorithm <execution type> Algorithm for the optimal route (default AStar)");
+        println!("  --output <json file>         Writes the report to a file");
+        return;
+    }
+
+    let mut coastlines = Vec::new();
+    let mut execution_type = ExecutionType::AStar;
+    let mut output_file = None;
+    for option in args[3..].chunks(2) {
+        if option.len() < 2 {
+            println!("Missing value for option {}", option[0]);
+            return;
+        }
+        match option[0].as_str() {
+            "--coastlines" => coastlines = Zone::new_from_geojson_file(&option[1]),
+            "--algorithm" => match FromStr::from_str(&option[1]) {
+                Ok(et) => execution_type = et,
+                Err(()) => {
+                    println!("Invalid execution type {}", &option[1]);
+                    return;
+                }
+            },
+            "--output" => output_file = Some(&option[1]),
+            _ => {
+                println!("Unknown option {}", option[0]);
+                return;
+            }
+        }
+    }
+
+    let waypoints = match import::import_route_file(&args[2]) {
+        Ok(waypoints) => waypoints,
+        Err(e) => {
+            println!("Invalid route: {}", e);
+            return;
+        }
+    };
+    println!("Imported route with {} waypoints", waypoints.len());
+
+    let graph = Graph::new_from_binfile(&args[1]);
+    let mut state = AlgorithmState::new(graph.node_count());
+    let validation = graph.validate_route(&waypoints, &coastlines, &execution_type, &mut state);
+
+    println!();
+    for leg in validation.legs.iter() {
+        if leg.crosses_coastline || !leg.land_cells.is_empty() {
+            println!(
+                "Leg {} from {},{} to {},{} ({:.1} nm): {}{} land cells",
+                leg.leg,
+                leg.from[0],
+                leg.from[1],
+                leg.to[0],
+                leg.to[1],
+                leg.distance,
+                if leg.crosses_coastline {
+                    "crosses the coastline, "
+                } else {
+                    ""
+                },
+                leg.land_cells.len()
+            );
+        }
+    }
+    println!("Route distance: {:.1} nm", validation.distance);
+    match (validation.optimal_distance, validation.detour) {
+        (Some(optimal_distance), Some(detour)) => println!(
+            "Optimal distance: {:.1} nm ({:.1}% detour)",
+            optimal_distance, detour
+        ),
+        _ => println!("No optimal route found"),
+    }
+    println!(
+        "The route is {}",
+        if validation.is_safe { "safe" } else { "unsafe" }
+    );
+
+    if let Some(output_file) = output_file {
+        fs::write(output_file, serde_json::to_string(&validation).unwrap()).unwrap();
+        println!("Report written to {}", output_file);
+    }
+}